@@ -18,6 +18,8 @@ export function useLobbyWebSocket(
   const [messages, setMessages] = useState<WSMessage[]>([]);
   const [lobbyState, setLobbyState] = useState<LobbyState>({});
   const wsRef = useRef<WebSocket | null>(null);
+  // seq of the last event applied to lobbyState.state
+  const seqRef = useRef(0);
 
   const sendMessage = useCallback((content: string) => {
     if (wsRef.current && wsRef.current.readyState === WebSocket.OPEN) {
@@ -47,11 +49,25 @@ export function useLobbyWebSocket(
       }
 
       if (data.type === "welcome") {
+        seqRef.current = data.seq ?? 0;
         setLobbyState({
           bundleMeta: data.bundleMeta,
           state: data.initialState,
         });
+      } else if (data.type === "snapshot") {
+        seqRef.current = data.seq;
+        setLobbyState((prev) => ({ ...prev, state: data.state }));
       } else if (data.diff && Array.isArray(data.diff)) {
+        if (typeof data.seq === "number") {
+          if (data.seq <= seqRef.current) return; // already applied
+          if (data.seq !== seqRef.current + 1) {
+            // missed an event: ask for everything after the last one applied
+            console.warn(`missed events ${seqRef.current + 1}..${data.seq - 1}, resyncing`);
+            ws.send(JSON.stringify({ type: "resync", since: seqRef.current }));
+            return;
+          }
+          seqRef.current = data.seq;
+        }
         setLobbyState((prev) => {
           if (!prev.state) return prev; // not initialized yet
           const nextState = applyPatch({ ...prev.state }, data.diff, true, false).newDocument;
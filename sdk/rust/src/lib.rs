@@ -1,4 +1,4 @@
-//! Bluefelt SDK 0.1.1 – minimal helpers for WASM hooks.
+//! Bluefelt SDK 0.1.3 – minimal helpers for WASM hooks.
 
 #![no_std]
 
@@ -30,11 +30,17 @@ mod host_raw {
         #[link_name = "get_grid"]
         pub fn host_get_grid(zone_ptr: *const u8, zone_len: u32, out_ptr: *mut u32) -> u32;
 
+        #[link_name = "random"]
+        pub fn host_random(bound: u32) -> u32;
+
         #[link_name = "advance_turn"]
         pub fn host_advance_turn();
 
         #[link_name = "round_end"]
         pub fn host_round_end(ptr: *const u8, len: u32);
+
+        #[link_name = "ask"]
+        pub fn host_ask(ptr: *const u8, len: u32);
     }
 }
 
@@ -94,6 +100,14 @@ pub mod host {
         serde_json::from_slice(bytes).unwrap()
     }
 
+    /* ------------------ Randomness ------------------ */
+
+    /// Uniform number in `0..bound` from the match's seeded RNG (0 if `bound` is 0).
+    /// Use this instead of any other source so matches stay replayable.
+    pub fn random(bound: u32) -> u32 {
+        unsafe { host_raw::host_random(bound) }
+    }
+
     /* ------------------ Control helpers ------------------ */
 
     pub fn advance_turn() {
@@ -103,6 +117,17 @@ pub mod host {
     pub fn round_end(winner: &str) {
         unsafe { host_raw::host_round_end(winner.as_ptr(), winner.len() as u32) }
     }
+
+    /* ------------------ Decisions ------------------ */
+
+    /// Ask a player to choose, e.g.
+    /// `{ "player":"p1", "choice":"PlayerId", "prompt":"…", "then":"guard_guess", "context":{…} }`.
+    /// The rest of the verb waits; the answer arrives as the `answer` field of
+    /// the `then` hook's payload, next to `context`.
+    pub fn ask(request: &JsonValue) {
+        let s = serde_json::to_string(request).unwrap();
+        unsafe { host_raw::host_ask(s.as_ptr(), s.len() as u32) }
+    }
 }
 
 /* ------------------------------------------------------------------------
@@ -1,41 +1,528 @@
-// No imports needed here - we're defining Bundle directly
-// Remove the circular import
-// use crate::bundle::Bundle;
+//! bundle.rs – discovery and loading of game bundles from `games/`
+//! Layout: games/<gameId>/<major.minor>/{manifest.yaml, entities.yaml, script.wasm}
 
+use crate::entities::{Rules, WinScore};
+use crate::hooks::Script;
+use crate::manifest::Manifest;
+use anyhow::{bail, Context};
+use parking_lot::RwLock;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// `major.minor` taken from the version folder name (e.g. `1.0`).
+pub type FolderVersion = (u64, u64);
+
+/// What to do when `manifest.hash` disagrees with the files on disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashPolicy {
+    /// refuse to load the bundle
+    Strict,
+    /// log the mismatch and load anyway (handy while editing a bundle)
+    Warn,
+}
+
+/// One loaded version of a game.
 #[derive(Clone)]
 pub struct Bundle {
     pub game_id: String,
-    // Add other fields as needed
+    pub version: semver::Version,
+    pub path: PathBuf,
+    pub manifest: Manifest,
+    pub rules: Rules,
+    /// `sha256-<hex>` content hash computed at load time; ties a match to this exact build
+    pub hash: String,
+    /// compiled `script.wasm`, when the bundle ships one
+    pub script: Option<Script>,
 }
 
+type GameIndex = HashMap<String, BTreeMap<semver::Version, Arc<Bundle>>>;
+
+/// Index of every bundle found under the games directory: gameId → version → bundle.
+/// Lobbies hold their own `Arc<Bundle>`, so a match stays on the build it started with
+/// even after [`BundleMap::reload`] swaps in a newer one.
 #[derive(Clone)]
 pub struct BundleMap {
-    // In a real implementation, this would be a map of game IDs to bundles
-    // For simplicity, we'll use a hardcoded approach for this example
+    root: PathBuf,
+    hash_policy: HashPolicy,
+    inner: Arc<RwLock<Index>>,
+}
+
+struct Index {
+    games: GameIndex,
+    errors: Vec<LoadError>,
+    loaded_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// A bundle folder that failed to load on the last scan.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadError {
+    pub path: String,
+    pub message: String,
+    /// an earlier build of the same folder is still being served
+    pub kept_previous: bool,
+}
+
+/// Outcome of a scan, as reported by the admin endpoints.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadReport {
+    pub loaded_at: chrono::DateTime<chrono::Utc>,
+    pub bundles: Vec<serde_json::Value>,
+    pub errors: Vec<LoadError>,
 }
 
 impl BundleMap {
-    pub fn load_dir(_path: &str) -> anyhow::Result<Self> {
-        // Implementation
-        Ok(Self { /* initialize fields */ })
+    /// Walk `games/<gameId>/<major.minor>/` and load every bundle found.
+    /// Broken bundles are logged and skipped so one bad game can't take the server down.
+    pub fn load_dir(path: &str, hash_policy: HashPolicy) -> anyhow::Result<Self> {
+        let root = PathBuf::from(path);
+        let (games, errors) = scan(&root, hash_policy, &GameIndex::new())?;
+        let index = Index { games, errors, loaded_at: chrono::Utc::now() };
+        Ok(Self { root, hash_policy, inner: Arc::new(RwLock::new(index)) })
     }
-    
-    pub fn get_latest(&self, game_id: &str) -> Option<Bundle> {
-        // For this example, we'll just return a bundle with the requested game_id
-        // In a real implementation, you'd lookup the game in a map
-        
-        // Only allow tic-tac-toe for now
-        if game_id == "tic-tac-toe" {
-            return Some(Bundle { 
-                game_id: game_id.to_string(),
-            });
-        }
-        
-        None
+
+    /// Re-scan the games directory and swap the result in for new lobbies.
+    /// A folder that no longer loads keeps serving its previous build.
+    pub fn reload(&self) -> anyhow::Result<LoadReport> {
+        let previous = self.inner.read().games.clone();
+        let (games, errors) = scan(&self.root, self.hash_policy, &previous)?;
+        *self.inner.write() = Index { games, errors, loaded_at: chrono::Utc::now() };
+        println!("[Bundle] Reloaded games from {}: {:?}", self.root.display(), self.list_games());
+        Ok(self.report())
+    }
+
+    /// What is currently loaded plus the errors of the last scan.
+    pub fn report(&self) -> LoadReport {
+        let index = self.inner.read();
+        let mut bundles: Vec<serde_json::Value> = index
+            .games
+            .values()
+            .flat_map(|versions| versions.values())
+            .map(|b| {
+                serde_json::json!({
+                    "gameId": b.game_id,
+                    "version": b.version.to_string(),
+                    "hash": b.hash,
+                    "path": b.path.display().to_string(),
+                })
+            })
+            .collect();
+        bundles.sort_by_key(|b| (b["gameId"].to_string(), b["version"].to_string()));
+        LoadReport { loaded_at: index.loaded_at, bundles, errors: index.errors.clone() }
+    }
+
+    /// Poll the games directory every `every` and reload when any file changes.
+    pub fn spawn_watcher(&self, every: std::time::Duration) -> tokio::task::JoinHandle<()> {
+        let bundles = self.clone();
+        tokio::spawn(async move {
+            let mut last = fingerprint_blocking(&bundles.root).await.unwrap_or_default();
+            let mut interval = tokio::time::interval(every);
+            loop {
+                interval.tick().await;
+                let Some(current) = fingerprint_blocking(&bundles.root).await else { continue };
+                if current == last {
+                    continue;
+                }
+                last = current;
+                println!("[Bundle] Change detected under {}, reloading", bundles.root.display());
+                let reloading = bundles.clone();
+                match tokio::task::spawn_blocking(move || reloading.reload()).await {
+                    Ok(Ok(_)) => {}
+                    Ok(Err(e)) => println!("[Bundle] ERROR: Reload failed: {:#}", e),
+                    Err(e) => println!("[Bundle] ERROR: Reload task panicked: {}", e),
+                }
+            }
+        })
+    }
+
+    /// Highest loaded version of a game.
+    pub fn get_latest(&self, game_id: &str) -> Option<Arc<Bundle>> {
+        self.inner
+            .read()
+            .games
+            .get(game_id)
+            .and_then(|versions| versions.values().next_back())
+            .cloned()
+    }
+
+    /// Highest loaded version of a game that satisfies `version_req`.
+    pub fn get(&self, game_id: &str, version_req: &semver::VersionReq) -> Option<Arc<Bundle>> {
+        self.inner
+            .read()
+            .games
+            .get(game_id)?
+            .iter()
+            .rev()
+            .find(|(version, _)| version_req.matches(version))
+            .map(|(_, bundle)| bundle.clone())
+    }
+
+    /// Every loaded version of a game, oldest first.
+    pub fn versions(&self, game_id: &str) -> Vec<semver::Version> {
+        self.inner
+            .read()
+            .games
+            .get(game_id)
+            .map(|versions| versions.keys().cloned().collect())
+            .unwrap_or_default()
     }
 
+    /// Ids of every game with at least one loaded version, sorted.
     pub fn list_games(&self) -> Vec<String> {
-        // For simplicity, just return tic-tac-toe
-        vec!["tic-tac-toe".to_string()]
+        let mut ids: Vec<String> = self.inner.read().games.keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+}
+
+/// Load every bundle under `root`. Folders that fail keep their build from
+/// `previous` (matched by path) if there is one.
+fn scan(root: &Path, hash_policy: HashPolicy, previous: &GameIndex) -> anyhow::Result<(GameIndex, Vec<LoadError>)> {
+    let game_dirs = std::fs::read_dir(root)
+        .with_context(|| format!("cannot read games directory {}", root.display()))?;
+
+    let mut games = GameIndex::new();
+    let mut errors = Vec::new();
+    for game_dir in sorted_subdirs(game_dirs)? {
+        let Some(game_id) = dir_name(&game_dir) else { continue };
+
+        let version_dirs = match std::fs::read_dir(&game_dir).map_err(anyhow::Error::from).and_then(sorted_subdirs) {
+            Ok(dirs) => dirs,
+            Err(e) => {
+                let kept = previous.get(&game_id).cloned().unwrap_or_default();
+                println!("[Bundle] ERROR: Skipping {}{}: cannot read it: {:#}",
+                    game_dir.display(), if kept.is_empty() { "" } else { " (keeping previous builds)" }, e);
+                errors.push(LoadError {
+                    path: game_dir.display().to_string(),
+                    message: format!("cannot read game directory: {:#}", e),
+                    kept_previous: !kept.is_empty(),
+                });
+                if !kept.is_empty() {
+                    games.insert(game_id, kept);
+                }
+                continue;
+            }
+        };
+        for version_dir in version_dirs {
+            let Some(folder_version) = dir_name(&version_dir).and_then(|n| parse_folder_version(&n)) else {
+                continue;
+            };
+
+            match Bundle::load(&game_id, folder_version, &version_dir, hash_policy) {
+                Ok(bundle) => {
+                    println!("[Bundle] Loaded {} {} (spec {}, {}) from {}",
+                        game_id, bundle.version, bundle.manifest.spec_version, bundle.hash, version_dir.display());
+                    games.entry(game_id.clone()).or_default().insert(bundle.version.clone(), Arc::new(bundle));
+                }
+                Err(e) => {
+                    let kept = previous
+                        .get(&game_id)
+                        .and_then(|versions| versions.values().find(|b| b.path == version_dir))
+                        .cloned();
+                    println!("[Bundle] ERROR: Skipping {}{}: {:#}",
+                        version_dir.display(), if kept.is_some() { " (keeping previous build)" } else { "" }, e);
+                    errors.push(LoadError {
+                        path: version_dir.display().to_string(),
+                        message: format!("{:#}", e),
+                        kept_previous: kept.is_some(),
+                    });
+                    if let Some(bundle) = kept {
+                        games.entry(game_id.clone()).or_default().insert(bundle.version.clone(), bundle);
+                    }
+                }
+            }
+        }
+    }
+    Ok((games, errors))
+}
+
+type Fingerprint = Vec<(PathBuf, u64, Option<std::time::SystemTime>)>;
+
+/// Cheap change detector for the watcher: every file's path, size and mtime.
+fn fingerprint(root: &Path) -> Fingerprint {
+    let mut files: Vec<_> = walkdir::WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|e| e.depth() == 0 || !skip_in_hash(e))
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| {
+            let meta = e.metadata().ok()?;
+            Some((e.into_path(), meta.len(), meta.modified().ok()))
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+/// `fingerprint` on the blocking pool, so walking the games directory
+/// doesn't hold up a runtime worker.
+async fn fingerprint_blocking(root: &Path) -> Option<Fingerprint> {
+    let root = root.to_path_buf();
+    match tokio::task::spawn_blocking(move || fingerprint(&root)).await {
+        Ok(files) => Some(files),
+        Err(e) => {
+            println!("[Bundle] ERROR: Scan for changes panicked: {}", e);
+            None
+        }
+    }
+}
+
+impl Bundle {
+    fn load(game_id: &str, folder_version: FolderVersion, dir: &Path, hash_policy: HashPolicy) -> anyhow::Result<Self> {
+        let manifest = Manifest::load(&dir.join("manifest.yaml"))?;
+        let rules = Rules::load(&dir.join("entities.yaml"))?;
+
+        if manifest.game_id != game_id {
+            bail!("manifest gameId `{}` does not match folder `{}`", manifest.game_id, game_id);
+        }
+        if (manifest.version.major, manifest.version.minor) != folder_version {
+            bail!(
+                "manifest version {} does not match folder {}.{}",
+                manifest.version, folder_version.0, folder_version.1
+            );
+        }
+
+        // a per-player-count `winScore` has to cover every count the manifest allows
+        if let Some(WinScore::ByPlayers(table)) = &rules.match_rules.win_score {
+            let players = &manifest.metadata.players;
+            if let Some(n) = (players.min..=players.max).find(|n| !table.contains_key(n)) {
+                if rules.match_rules.rounds.is_none() {
+                    bail!("entities.yaml: `match.winScore` has no entry for {} players and `match.rounds` is not set", n);
+                }
+            }
+        }
+
+        let wasm = dir.join("script.wasm");
+        let script = if wasm.is_file() {
+            Some(Script::load(&wasm, &rules.hooks)?)
+        } else {
+            if !rules.hooks.is_empty() {
+                println!("[Bundle] WARNING: {} registers hooks but has no script.wasm; they will not run", dir.display());
+            }
+            None
+        };
+
+        let hash = content_hash(dir)?;
+        match &manifest.hash {
+            Some(declared) if *declared != hash => match hash_policy {
+                HashPolicy::Strict => bail!("manifest hash {} does not match bundle contents ({})", declared, hash),
+                HashPolicy::Warn => println!(
+                    "[Bundle] WARNING: {} declares hash {} but contents hash to {}",
+                    dir.display(), declared, hash
+                ),
+            },
+            Some(_) => {}
+            None => println!("[Bundle] {} declares no hash; computed {}", dir.display(), hash),
+        }
+
+        Ok(Self {
+            game_id: game_id.to_string(),
+            version: manifest.version.clone(),
+            path: dir.to_path_buf(),
+            manifest,
+            rules,
+            hash,
+            script,
+        })
+    }
+
+    /// `bundleMeta` block of the welcome message: identity plus the
+    /// client-facing parts of the rules.
+    pub fn meta(&self) -> serde_json::Value {
+        serde_json::json!({
+            "gameId": self.game_id,
+            "version": self.version.to_string(),
+            "name": self.manifest.metadata.name,
+            "hash": self.hash,
+            "entities": self.rules.entities,
+            "zones": self.rules.zones,
+            "verbs": self.rules.verbs,
+            "phases": self.rules.phases,
+            "match": self.rules.match_rules,
+        })
+    }
+}
+
+/* --------------------------------------------------------------------------
+   content hash
+   ----------------------------------------------------------------------- */
+
+/// Canonical `sha256-<hex>` hash of a bundle directory.
+///
+/// Every regular file is fed in byte order of its `/`-separated relative path
+/// as `path \0 length \0 bytes`. Dotfiles and `target/` build output are
+/// skipped, and `manifest.yaml` is hashed without its `hash:` line (see
+/// [`strip_manifest_hash`]) so the hash can be written back into it.
+pub fn content_hash(dir: &Path) -> anyhow::Result<String> {
+    let mut files = Vec::new();
+    let walker = walkdir::WalkDir::new(dir).into_iter().filter_entry(|e| e.depth() == 0 || !skip_in_hash(e));
+    for entry in walker {
+        let entry = entry.with_context(|| format!("cannot walk {}", dir.display()))?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let rel = entry.path().strip_prefix(dir)?;
+        let rel = rel.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/");
+        files.push((rel, entry.into_path()));
+    }
+    files.sort();
+
+    let mut hasher = Sha256::new();
+    for (rel, path) in files {
+        let mut bytes = std::fs::read(&path).with_context(|| format!("cannot read {}", path.display()))?;
+        if rel == "manifest.yaml" {
+            bytes = strip_manifest_hash(&bytes);
+        }
+        hasher.update(rel.as_bytes());
+        hasher.update([0]);
+        hasher.update(bytes.len().to_string().as_bytes());
+        hasher.update([0]);
+        hasher.update(&bytes);
+    }
+    let hex: String = hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect();
+    Ok(format!("sha256-{}", hex))
+}
+
+fn skip_in_hash(entry: &walkdir::DirEntry) -> bool {
+    let name = entry.file_name().to_string_lossy();
+    name.starts_with('.') || (entry.file_type().is_dir() && name == "target")
+}
+
+/// `manifest.yaml` without its top-level `hash:` line, with line endings and
+/// trailing blank lines normalized so adding the hash doesn't change the result.
+fn strip_manifest_hash(bytes: &[u8]) -> Vec<u8> {
+    let text = String::from_utf8_lossy(bytes);
+    let lines: Vec<&str> = text
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !(line.starts_with("hash:") || line.starts_with("\"hash\":")))
+        .collect();
+    let end = lines.iter().rposition(|l| !l.is_empty()).map_or(0, |i| i + 1);
+    lines[..end].join("\n").into_bytes()
+}
+
+/* --------------------------------------------------------------------------
+   helpers
+   ----------------------------------------------------------------------- */
+
+/// Sub-directories of a `read_dir`, sorted by name so loading order is stable.
+fn sorted_subdirs(entries: std::fs::ReadDir) -> anyhow::Result<Vec<PathBuf>> {
+    let mut dirs = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            dirs.push(entry.path());
+        }
+    }
+    dirs.sort();
+    Ok(dirs)
+}
+
+/// Directory name, skipping hidden entries like `.git`.
+fn dir_name(path: &Path) -> Option<String> {
+    let name = path.file_name()?.to_str()?;
+    if name.starts_with('.') {
+        return None;
+    }
+    Some(name.to_string())
+}
+
+/// Parse a version folder name such as `1.0` into `(1, 0)`.
+fn parse_folder_version(name: &str) -> Option<FolderVersion> {
+    let (major, minor) = name.split_once('.')?;
+    Some((major.parse().ok()?, minor.parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = "gameId: \"demo\"\nversion: \"1.0.0\"\nspecVersion: \"1\"\n\nmetadata:\n  name: \"Demo\"\n  players: { min: 2, max: 2 }\n";
+
+    /// A scratch bundle folder with a manifest and one other file.
+    fn bundle_dir(name: &str, manifest: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("bluefelt-hash-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("manifest.yaml"), manifest).unwrap();
+        std::fs::write(dir.join("entities.yaml"), "entities: {}\n").unwrap();
+        dir
+    }
+
+    #[test]
+    fn strip_manifest_hash_ignores_the_hash_line_and_line_endings() {
+        let plain = strip_manifest_hash(MANIFEST.as_bytes());
+        let hashed = format!("{}hash: \"sha256-0123\"\n\n", MANIFEST);
+        let quoted = format!("\"hash\": \"sha256-0123\"\n{}", MANIFEST);
+        assert_eq!(strip_manifest_hash(hashed.as_bytes()), plain);
+        assert_eq!(strip_manifest_hash(quoted.as_bytes()), plain);
+        assert_eq!(strip_manifest_hash(MANIFEST.replace('\n', "\r\n").as_bytes()), plain);
+    }
+
+    #[test]
+    fn content_hash_is_the_same_with_or_without_the_hash_line() {
+        let dir = bundle_dir("stable", MANIFEST);
+        let computed = content_hash(&dir).unwrap();
+        std::fs::write(dir.join("manifest.yaml"), format!("{}hash: \"{}\"\n", MANIFEST, computed)).unwrap();
+        assert_eq!(content_hash(&dir).unwrap(), computed);
+
+        std::fs::write(dir.join("entities.yaml"), "entities: { chip: { kind: token } }\n").unwrap();
+        assert_ne!(content_hash(&dir).unwrap(), computed, "other files still count");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    const RULES: &str = "entities:\n  chip: { kind: token }\nzones:\n  pile: { shape: stack, visibility: all }\nverbs: {}\nphases:\n  - id: turn\n    verbs: []\n";
+
+    /// A scratch games directory holding `demo` at each of `versions`, one
+    /// folder per `major.minor`.
+    fn games_dir(name: &str, versions: &[&str], rules: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("bluefelt-games-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&root);
+        for version in versions {
+            let v = semver::Version::parse(version).unwrap();
+            let dir = root.join("demo").join(format!("{}.{}", v.major, v.minor));
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join("manifest.yaml"), MANIFEST.replace("1.0.0", version)).unwrap();
+            std::fs::write(dir.join("entities.yaml"), rules).unwrap();
+        }
+        root
+    }
+
+    #[test]
+    fn get_picks_the_highest_version_matching_the_requirement() {
+        let root = games_dir("get", &["1.0.0", "1.2.3", "2.0.1"], RULES);
+        let bundles = BundleMap::load_dir(root.to_str().unwrap(), HashPolicy::Strict).unwrap();
+        let version = |req: &str| {
+            bundles.get("demo", &semver::VersionReq::parse(req).unwrap()).map(|b| b.version.to_string())
+        };
+
+        assert_eq!(version("^1").as_deref(), Some("1.2.3"));
+        assert_eq!(version("~1.0").as_deref(), Some("1.0.0"));
+        assert_eq!(version("*").as_deref(), Some("2.0.1"));
+        assert_eq!(version("^3"), None, "no loaded version matches");
+        assert!(bundles.get("other", &semver::VersionReq::STAR).is_none(), "unknown game");
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn a_win_score_table_covers_every_player_count_unless_rounds_are_set() {
+        let load = |name: &str, match_rules: &str| {
+            let root = games_dir(name, &["1.0.0"], &format!("{}match: {}\n", RULES, match_rules));
+            let loaded = Bundle::load("demo", (1, 0), &root.join("demo").join("1.0"), HashPolicy::Strict);
+            std::fs::remove_dir_all(&root).unwrap();
+            loaded.map(|_| ()).map_err(|e| format!("{:#}", e))
+        };
+        // the manifest seats exactly two
+        assert_eq!(
+            load("win-missing", "{ winScore: { 3: 5, 4: 4 } }").unwrap_err(),
+            "entities.yaml: `match.winScore` has no entry for 2 players and `match.rounds` is not set"
+        );
+        load("win-covered", "{ winScore: { 2: 7, 3: 5 } }").unwrap();
+        load("win-rounds", "{ winScore: { 3: 5 }, rounds: 3 }").unwrap();
+        assert!(load("win-zero", "{ winScore: 0 }").unwrap_err().contains("a match is won with at least one point"));
     }
 }
@@ -1,152 +1,851 @@
-use crate::{bundle::Bundle, engine};
-use axum::extract::ws::{Message, WebSocket};
-use dashmap::DashMap;
-use futures_util::{StreamExt, SinkExt};
+//! engine.rs – data-driven rules interpreter
+//! Builds the initial state from a bundle's zones/entities/setup, then applies
+//! verbs: `pre` checks → `effect` list → `nextPhase`.
+
+mod effects;
+mod events;
+mod handles;
+mod legal;
+mod log;
+mod params;
+mod patch;
+mod pending;
+mod phases;
+mod predicates;
+mod resolve;
+mod rng;
+mod rounds;
+mod tick;
+mod visibility;
+
+use crate::bundle::Bundle;
+use crate::hooks::{Budget, HookRuntime};
+use crate::entities::{Call, HookEvent, ParamSpec, ParamType, Rules, SetupStep, VerbTemplate, ZoneShape, ZoneTemplate};
+use anyhow::{anyhow, bail};
+use patch::escape;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::fmt;
 use std::sync::Arc;
-use tokio::sync::broadcast;
-use tokio::sync::Mutex;
 
-#[allow(dead_code)]
-pub type LobbyMap = DashMap<String, Arc<Lobby>>;
+pub use patch::apply as apply_patch_op;
+pub use log::MatchLog;
+pub use pending::ANSWER;
+pub use rng::{fresh_seed, Rng};
+pub use tick::Tick;
+
+pub type State = serde_json::Value;
+
+/// Implicit bag for entities that are not on the table yet (tic-tac-toe's unused
+/// marks). Always present; bundles may declare it to pick a shape/visibility.
+pub const BANK: &str = "bank";
+
+/// Per-player flag zone that takes a seat out of the turn order when set.
+pub const ELIMINATED: &str = "eliminated";
+
+/// Seat id of the n-th player to join (`p1`, `p2`, …). Rules refer to seats, not user names.
+pub fn seat_id(index: usize) -> String {
+    format!("p{}", index + 1)
+}
+
+/// One running game: the bundle it is pinned to and its authoritative state.
+///
+/// State layout:
+/// ```text
+/// { "players":  [{ "id": "p1", "name": "alice", ... }],
+///   "zones":    { "deck": [ids], "hands": { "p1": [ids] }, "board": [[id|null]], "immunes": { "p1": false } },
+///   "entities": { "<id>": { "template": "guard" } },
+///   "turn":     { "player": "p1", "phase": "turn", "tick": 1 },
+///   "round":    1,
+///   "scores":   { "p1": 0 },
+///   "pending":  { "player": "p1", "choice": "PlayerId", "prompt": "...", "resume": {...} },  // while a question is open
+///   "result":   { "winner": "p1" | null, "scores": { "p1": 1 } } }   // once the match is over
+/// ```
+pub struct Match {
+    pub bundle: Arc<Bundle>,
+    pub state: State,
+    /// seeded at creation; with the same seed and actions the match replays exactly
+    rng: Rng,
+    /// every accepted action so far
+    pub log: MatchLog,
+    /// ops applied since the current verb started; becomes its diff
+    patch: Vec<Value>,
+    /// this match's instance of the bundle's `script.wasm`
+    hooks: Option<HookRuntime>,
+    /// fuel and time the hooks of the current tick have left
+    hook_budget: Budget,
+    /// set once the turn has moved on during the current verb
+    turn_passed: bool,
+    /// set when passing the turn took play back into the turn phase, whose
+    /// `on_phase_start` hooks are still to run
+    entered_turn_phase: bool,
+    /// winner (or `null`) once a hook has ended the round during the current verb
+    round_over: Option<Value>,
+    /// `{ actor, verb, args }` of the verb being applied; hook payloads repeat it
+    invocation: Value,
+}
+
+/// Who is acting and with which verb arguments; `$param` and `actor` resolve against it.
+struct Ctx<'a> {
+    actor: Option<&'a str>,
+    params: &'a Map<String, Value>,
+}
+
+/* --------------------------------------------------------------------------
+   rejections
+   ----------------------------------------------------------------------- */
+
+/// Why a verb was refused. The state is unchanged when this is returned.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Rejection {
+    pub code: RejectCode,
+    pub reason: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failed_precondition: Option<FailedPrecondition>,
+}
 
-#[allow(dead_code)]
-pub fn new_lobby(id: String, bundle: Bundle) -> Arc<Lobby> {
-    Arc::new(Lobby::new(id, bundle))
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RejectCode {
+    UnknownVerb,
+    WrongPhase,
+    NotYourTurn,
+    PreconditionFailed,
+    EffectFailed,
+    /// a hook trapped, ran out of fuel or timed out (`BLUEFELT_HOOK_FAILURE=abort`)
+    HookFailed,
+    GameOver,
+    /// `args` don't match the verb's declared params
+    InvalidArgs,
+    /// not JSON, or not a `{verb, args}` message
+    BadMessage,
+    /// a verb sent before the lobby has enough players
+    NotStarted,
+    /// `start` too early, too late, or from a socket without a seat
+    CannotStart,
+    /// a question is waiting for an answer, so no other verb is accepted
+    DecisionPending,
+    /// `answer` sent when nothing was asked
+    NothingToAnswer,
 }
 
-pub struct Lobby {
-    pub id: String,
-    pub bundle: Bundle,
-    state: Mutex<serde_json::Value>,
-    tx: broadcast::Sender<Message>,
+/// The `pre:` entry that did not hold, with its arguments after substitution.
+#[derive(Debug, Clone, Serialize)]
+pub struct FailedPrecondition {
+    pub name: String,
+    pub index: usize,
+    pub args: Value,
 }
 
-impl Lobby {
-    pub fn new(id: String, bundle: Bundle) -> Self {
-        let initial = engine::load_initial_state(&bundle);
-        let (tx, _) = broadcast::channel(32);
-        Self { id, bundle, state: Mutex::new(initial), tx }
+impl Rejection {
+    pub fn new(code: RejectCode, reason: impl Into<String>) -> Self {
+        Self { code, reason: reason.into(), failed_precondition: None }
     }
-    pub fn players(&self) -> usize { self.tx.receiver_count() }
+}
 
-    pub async fn accept_client(self: Arc<Self>, socket: WebSocket) {
-        let (mut ws_tx, mut ws_rx) = socket.split();
-        let mut rx = self.tx.subscribe();
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.failed_precondition {
+            Some(p) => write!(f, "{} (`{}` failed)", self.reason, p.name),
+            None => write!(f, "{}", self.reason),
+        }
+    }
+}
+
+/* --------------------------------------------------------------------------
+   Match
+   ----------------------------------------------------------------------- */
 
-        /* send welcome snapshot */
-        let snap = {
-            let s = self.state.lock().await;
-            s.clone()
+impl Match {
+    /// Lay out the zones for the seated players, create entity instances and run `setup`.
+    /// Everything random in the match follows from `seed`.
+    pub fn new(bundle: Arc<Bundle>, players: &[String], seed: u64) -> anyhow::Result<Self> {
+        let rules = &bundle.rules;
+        let seats: Vec<String> = (0..players.len()).map(seat_id).collect();
+
+        let state = json!({
+            "players": seats.iter().zip(players).map(|(seat, name)| json!({ "id": seat, "name": name })).collect::<Vec<_>>(),
+            "zones": empty_zones(rules, &seats),
+            "entities": {},
+            "turn": Self::initial_turn(&seats, rules.phases.first().map(|p| p.id.as_str())),
+            "round": 1,
+            "scores": seats.iter().map(|s| (s.clone(), json!(0))).collect::<Map<_, _>>(),
+        });
+        let hooks = bundle.script.as_ref().map(|s| s.instantiate()).transpose()?;
+        let mut m = Self {
+            bundle: bundle.clone(),
+            state,
+            rng: Rng::new(seed),
+            log: MatchLog::default(),
+            patch: Vec::new(),
+            hooks,
+            hook_budget: Budget::start(),
+            turn_passed: false,
+            entered_turn_phase: false,
+            round_over: None,
+            invocation: Value::Null,
         };
-        let welcome = serde_json::json!({
-            "type":"welcome",
-            "bundleMeta":{},
-            "initialState": snap
+        m.deal()?;
+        // the initial state is sent whole, not as a diff
+        m.patch.clear();
+        m.log = MatchLog::start(&m, players, seed);
+        Ok(m)
+    }
+
+    /// Apply `{ "verb": ..., "args": {...} }` from `actor` (a seat id), log it and
+    /// return the tick with its diff. The verb and every hook it triggers form one
+    /// tick: on rejection, whatever part of it failed, the state is left untouched.
+    /// `answer` goes to the open question instead (see pending.rs).
+    pub fn apply_verb(&mut self, actor: &str, action: &Value) -> Result<Tick, Rejection> {
+        if self.is_over() {
+            return Err(Rejection::new(RejectCode::GameOver, "the game is over"));
+        }
+        let bundle = self.bundle.clone();
+        let name = action["verb"].as_str().unwrap_or_default();
+        if name == ANSWER {
+            return self.answer(actor, &action["args"]);
+        }
+        if let Some(player) = self.pending_player() {
+            return Err(Rejection::new(RejectCode::DecisionPending, format!("waiting for {} to answer", player)));
+        }
+        let verb = bundle
+            .rules
+            .verbs
+            .get(name)
+            .ok_or_else(|| Rejection::new(RejectCode::UnknownVerb, format!("unknown verb `{}`", name)))?;
+        self.check_params(verb, &action["args"], actor)?;
+        let no_params = Map::new();
+        let params = action["args"].as_object().unwrap_or(&no_params);
+        let ctx = Ctx { actor: Some(actor), params };
+
+        let phase_id = self.phase_id().to_string();
+        if !bundle.rules.phase(&phase_id).is_some_and(|p| p.verbs.iter().any(|v| v == name)) {
+            return Err(Rejection::new(
+                RejectCode::WrongPhase,
+                format!("`{}` is not allowed in phase `{}`", name, phase_id),
+            ));
+        }
+        if !self.active_players().iter().any(|p| p == actor) {
+            let reason = match self.turn_player() {
+                Some(turn) if turn != actor => format!("it is {}'s turn, not {}'s", turn, actor),
+                _ => format!("{} cannot act in phase `{}`", actor, phase_id),
+            };
+            return Err(Rejection::new(RejectCode::NotYourTurn, reason));
+        }
+        self.check_pre(verb, &ctx)?;
+
+        // the entity the verb plays (Love Letter's `cardId`), whose `hook:` fires after it
+        let entity = verb
+            .played_param()
+            .and_then(|name| params.get(name))
+            .and_then(Value::as_str)
+            .filter(|id| self.template_of(id).is_some())
+            .map(str::to_string);
+
+        self.invocation = json!({ "actor": actor, "verb": name, "args": params });
+        let mut round_end = None;
+        let result = self.transaction(|m| {
+            m.next_tick()?;
+            m.resolve_verb(verb, &phase_id, &ctx, entity.as_deref(), 0, 0)?;
+            round_end = m.close_round()?;
+            Ok(())
         });
-        let _ = ws_tx.send(Message::Text(welcome.to_string())).await;
-
-        /* spawn task to forward events */
-        let forward = {
-            let ws_tx = Arc::new(tokio::sync::Mutex::new(ws_tx));
-            let ws_tx_copy = ws_tx.clone();
-            tokio::spawn(async move {
-                while let Ok(msg) = rx.recv().await {
-                    let mut guard = ws_tx_copy.lock().await;
-                    let _ = guard.send(msg).await;
+        self.invocation = Value::Null;
+        self.settle(result, round_end)
+    }
+
+    /// Log a tick that went through, or turn what went wrong into a rejection.
+    fn settle(&mut self, result: anyhow::Result<Tick>, round_end: Option<rounds::RoundEnd>) -> Result<Tick, Rejection> {
+        let mut tick = result.map_err(|e| {
+            let code = match e.downcast_ref::<events::HookFailure>() {
+                Some(_) => RejectCode::HookFailed,
+                None => RejectCode::EffectFailed,
+            };
+            Rejection::new(code, format!("{:#}", e))
+        })?;
+        tick.round_end = round_end;
+        self.log.record(&tick);
+        Ok(tick)
+    }
+
+    /* ----------------------------------------------------------------------
+       setup
+       ------------------------------------------------------------------- */
+
+    /// Put every entity no `initZone` places into the supply zone, then run `setup`.
+    /// Once for a new match and again at the start of every round.
+    fn deal(&mut self) -> anyhow::Result<()> {
+        let bundle = self.bundle.clone();
+        let rules = &bundle.rules;
+        let supply = self.supply_zone();
+        for (template, entity) in &rules.entities {
+            if !places_entity(&rules.setup, template) {
+                for _ in 0..entity.count {
+                    self.spawn(template, &supply, None)?;
+                }
+            }
+        }
+        let no_params = Map::new();
+        self.run_setup(&rules.setup, &Ctx { actor: None, params: &no_params })
+    }
+
+    fn run_setup(&mut self, steps: &[SetupStep], ctx: &Ctx) -> anyhow::Result<()> {
+        let bundle = self.bundle.clone();
+        for step in steps {
+            match step {
+                SetupStep::Repeat { times, actions } => {
+                    for _ in 0..*times {
+                        self.run_setup(actions, ctx)?;
+                    }
+                }
+                SetupStep::ForEachPlayer(actions) => {
+                    for seat in self.seats() {
+                        self.run_setup(actions, &Ctx { actor: Some(&seat), params: ctx.params })?;
+                    }
+                }
+                SetupStep::Verb(name) => {
+                    let verb = bundle.rules.verbs.get(name).ok_or_else(|| anyhow!("setup: unknown verb `{}`", name))?;
+                    for effect in &verb.effect {
+                        self.run_effect(effect, ctx)?;
+                    }
                 }
-            })
+                SetupStep::Effect(call) => self.run_effect(call, ctx)?,
+            }
+        }
+        Ok(())
+    }
+
+    /* ----------------------------------------------------------------------
+       preconditions
+       ------------------------------------------------------------------- */
+
+    /// Evaluate `pre:` in order; the first one that fails is reported.
+    fn check_pre(&self, verb: &VerbTemplate, ctx: &Ctx) -> Result<(), Rejection> {
+        for (index, cond) in verb.pre.iter().enumerate() {
+            let failed = |reason: String, args: Value| Rejection {
+                code: RejectCode::PreconditionFailed,
+                reason,
+                failed_precondition: Some(FailedPrecondition { name: cond.name.clone(), index, args }),
+            };
+            let predicate = predicates::lookup(&cond.name)
+                .ok_or_else(|| failed(format!("unknown precondition `{}`", cond.name), json!(cond.args)))?;
+            let args = self.resolve_args(&cond.args, ctx).map_err(|e| failed(format!("{:#}", e), json!(cond.args)))?;
+            (predicate.eval)(self, &args).map_err(|e| failed(format!("{:#}", e), Value::Object(args.values.clone())))?;
+        }
+        Ok(())
+    }
+
+    /// Per-player flag zone such as `immunes` or `eliminated`.
+    fn flag(&self, zone: &str, player: &str) -> anyhow::Result<bool> {
+        if !self.seats().iter().any(|s| s == player) {
+            bail!("unknown player `{}`", player);
+        }
+        Ok(self.zone(zone, Some(player))? == true)
+    }
+
+    /* ----------------------------------------------------------------------
+       effects
+       ------------------------------------------------------------------- */
+
+    fn run_effect(&mut self, effect: &Call, ctx: &Ctx) -> anyhow::Result<()> {
+        let primitive = effects::lookup(&effect.name).ok_or_else(|| anyhow!("unsupported effect `{}`", effect.name))?;
+        let args = self.resolve_args(&effect.args, ctx)?;
+        (primitive.run)(self, &args).map_err(|e| anyhow!("`{}`: {:#}", effect.name, e))
+    }
+
+    /* ----------------------------------------------------------------------
+       recorded mutations
+       ------------------------------------------------------------------- */
+
+    fn add(&mut self, path: String, value: Value) -> anyhow::Result<()> {
+        patch::add(&mut self.state, &path, value.clone())?;
+        self.patch.push(json!({ "op": "add", "path": path, "value": value }));
+        Ok(())
+    }
+
+    fn remove(&mut self, path: String) -> anyhow::Result<Value> {
+        let removed = patch::remove(&mut self.state, &path)?;
+        self.patch.push(json!({ "op": "remove", "path": path }));
+        Ok(removed)
+    }
+
+    fn replace(&mut self, path: String, value: Value) -> anyhow::Result<()> {
+        patch::replace(&mut self.state, &path, value.clone())?;
+        self.patch.push(json!({ "op": "replace", "path": path, "value": value }));
+        Ok(())
+    }
+
+    /* ----------------------------------------------------------------------
+       zones + entities
+       ------------------------------------------------------------------- */
+
+    pub fn seats(&self) -> Vec<String> {
+        self.state["players"]
+            .as_array()
+            .map(|ps| ps.iter().filter_map(|p| p["id"].as_str().map(str::to_string)).collect())
+            .unwrap_or_default()
+    }
+
+    fn seat_index(&self, seat: &str) -> Option<usize> {
+        self.state["players"].as_array()?.iter().position(|p| p["id"] == seat)
+    }
+
+    /// First per-player list zone (Love Letter's `hands`).
+    fn hand_zone(&self) -> Option<String> {
+        self.bundle
+            .rules
+            .zones
+            .iter()
+            .find(|(_, z)| z.per_player && matches!(z.shape, ZoneShape::List | ZoneShape::Stack | ZoneShape::Queue | ZoneShape::Bag))
+            .map(|(id, _)| id.clone())
+    }
+
+    /// First shared pile declared in the rules (Love Letter's `deck`), else the bank.
+    fn supply_zone(&self) -> String {
+        self.bundle
+            .rules
+            .zones
+            .iter()
+            .find(|(_, z)| !z.per_player && matches!(z.shape, ZoneShape::Stack | ZoneShape::Queue | ZoneShape::Bag | ZoneShape::List))
+            .map(|(id, _)| id.clone())
+            .unwrap_or_else(|| BANK.to_string())
+    }
+
+    fn zone_pointer(&self, zone: &str, slot: Option<&str>) -> anyhow::Result<String> {
+        match self.bundle.rules.zones.get(zone) {
+            Some(z) if z.per_player => {
+                let slot = slot.ok_or_else(|| anyhow!("zone `{}` is per player but no player was given", zone))?;
+                Ok(format!("/zones/{}/{}", zone, slot))
+            }
+            Some(_) => Ok(format!("/zones/{}", zone)),
+            None if zone == BANK => Ok(format!("/zones/{}", BANK)),
+            None => bail!("unknown zone `{}`", zone),
+        }
+    }
+
+    fn zone(&self, zone: &str, slot: Option<&str>) -> anyhow::Result<&Value> {
+        let pointer = self.zone_pointer(zone, slot)?;
+        self.state.pointer(&pointer).ok_or_else(|| anyhow!("no zone at {}", pointer))
+    }
+
+    /// Pointer of a zone that is about to change; immutable zones refuse.
+    fn mutable_zone(&self, zone: &str, slot: Option<&str>) -> anyhow::Result<String> {
+        if self.bundle.rules.zones.get(zone).is_some_and(|z| !z.mutable) {
+            bail!("zone `{}` is not mutable", zone);
+        }
+        self.zone_pointer(zone, slot)
+    }
+
+    fn template_of(&self, id: &str) -> Option<&str> {
+        self.state["entities"][id]["template"].as_str()
+    }
+
+    /// Position of an entity in a list zone, by instance id or template id.
+    fn find_in(&self, zone: &Value, entity: &str) -> Option<usize> {
+        zone.as_array()?
+            .iter()
+            .position(|v| v.as_str().is_some_and(|id| id == entity || self.template_of(id) == Some(entity)))
+    }
+
+    /// Create a new instance of `template` in `zone`.
+    fn spawn(&mut self, template: &str, zone: &str, slot: Option<&str>) -> anyhow::Result<String> {
+        if !self.bundle.rules.entities.contains_key(template) {
+            bail!("unknown entity `{}`", template);
+        }
+        let id = self.new_handle();
+        self.add(format!("/entities/{}", escape(&id)), json!({ "template": template }))?;
+        self.put(zone, slot, id.clone(), None)?;
+        Ok(id)
+    }
+
+    /// Remove an entity from a zone: the named one, or the top/front one.
+    fn take(&mut self, zone: &str, slot: Option<&str>, entity: Option<&str>) -> anyhow::Result<String> {
+        let pile = self.zone(zone, slot)?.as_array().ok_or_else(|| anyhow!("cannot take entities from zone `{}`", zone))?;
+        let index = match entity {
+            Some(e) => self.find_in(self.zone(zone, slot)?, e).ok_or_else(|| anyhow!("`{}` is not in `{}`", e, zone))?,
+            None if pile.is_empty() => bail!("zone `{}` is empty", zone),
+            None => 0,
         };
+        let pointer = self.mutable_zone(zone, slot)?;
+        let removed = self.remove(format!("{}/{}", pointer, index))?;
+        Ok(removed.as_str().unwrap_or_default().to_string())
+    }
+
+    /// Add an entity to a zone. Stacks grow at the top (index 0), other piles at the end;
+    /// grids need a free `(row, col)`.
+    fn put(&mut self, zone: &str, slot: Option<&str>, id: String, cell: Option<(usize, usize)>) -> anyhow::Result<()> {
+        let shape = self.bundle.rules.zones.get(zone).map_or(ZoneShape::Bag, |z| z.shape);
+        let pointer = self.mutable_zone(zone, slot)?;
+        match shape {
+            ZoneShape::Grid => {
+                let (r, c) = cell.ok_or_else(|| anyhow!("moving into grid `{}` needs row and col", zone))?;
+                let target = format!("{}/{}/{}", pointer, r, c);
+                match self.state.pointer(&target) {
+                    Some(Value::Null) => self.replace(target, json!(id)),
+                    Some(_) => bail!("cell {},{} of `{}` is occupied", r, c, zone),
+                    None => bail!("cell {},{} is off the grid", r, c),
+                }
+            }
+            ZoneShape::Flag => bail!("cannot put entities into flag zone `{}`", zone),
+            ZoneShape::Stack => self.add(format!("{}/0", pointer), json!(id)),
+            _ => self.add(format!("{}/-", pointer), json!(id)),
+        }
+    }
 
-        /* read loop */
-        while let Some(Ok(Message::Text(t))) = ws_rx.next().await {
-            if let Ok(json) = serde_json::from_str::<serde_json::Value>(&t) {
-                if json["verb"] == "place" {
-                    let mut state_guard = self.state.lock().await;
-                    let diff = engine::apply_verb(&self.bundle, &mut *state_guard, &json);
-                    let event = serde_json::json!({
-                        "type":"event",
-                        "t":0,
-                        "actor":"p1",
-                        "verb": json["verb"],
-                        "diff": diff
-                    });
-                    let _ = self.tx.send(Message::Text(event.to_string()));
+    /// Set a flag zone (per-player ones need `player`).
+    fn set_flag(&mut self, zone: &str, player: Option<&str>, value: bool) -> anyhow::Result<()> {
+        if self.bundle.rules.zones.get(zone).map(|z| z.shape) != Some(ZoneShape::Flag) {
+            bail!("`{}` is not a flag zone", zone);
+        }
+        if let Some(p) = player {
+            if self.seat_index(p).is_none() {
+                bail!("unknown player `{}`", p);
+            }
+        }
+        let pointer = self.mutable_zone(zone, player)?;
+        if self.state.pointer(&pointer) == Some(&json!(value)) {
+            return Ok(());
+        }
+        self.replace(pointer, json!(value))
+    }
+
+    /* ----------------------------------------------------------------------
+       argument substitution
+       ------------------------------------------------------------------- */
+
+    /// Substitute every argument of a call: `actor` → acting seat, `$param` →
+    /// verb argument, `$actorMark` → the actor's `mark` attribute.
+    fn resolve_args(&self, raw: &Map<String, Value>, ctx: &Ctx) -> anyhow::Result<Args> {
+        let mut values = Map::new();
+        for (key, value) in raw {
+            values.insert(key.clone(), self.resolve(value, ctx)?);
+        }
+        Ok(Args { actor: ctx.actor.map(str::to_string), values })
+    }
+
+    fn resolve(&self, value: &Value, ctx: &Ctx) -> anyhow::Result<Value> {
+        match value.as_str() {
+            Some("actor") => ctx.actor.map(|a| json!(a)).ok_or_else(|| anyhow!("`actor` used outside a player action")),
+            Some(s) if s.starts_with('$') => {
+                let name = &s[1..];
+                if let Some(v) = ctx.params.get(name) {
+                    return Ok(v.clone());
+                }
+                if let Some(attr) = actor_attribute(name) {
+                    let actor = ctx.actor.ok_or_else(|| anyhow!("`{}` used outside a player action", s))?;
+                    let player = self.state["players"].as_array().and_then(|ps| ps.iter().find(|p| p["id"] == actor));
+                    return player
+                        .and_then(|p| p.get(&attr))
+                        .cloned()
+                        .ok_or_else(|| anyhow!("player {} has no `{}`", actor, attr));
                 }
+                bail!("missing argument `{}`", name)
             }
+            _ => Ok(value.clone()),
         }
-        forward.abort();
     }
 }
 
-pub type State = serde_json::Value;
+/* --------------------------------------------------------------------------
+   call arguments
+   ----------------------------------------------------------------------- */
 
-pub fn load_initial_state(_bundle: &Bundle) -> serde_json::Value {
-    // Return a properly structured initial state for a tic-tac-toe game
-    serde_json::json!({
-        "zones": {
-            "board": [
-                [null, null, null],
-                [null, null, null],
-                [null, null, null]
-            ]
-        },
-        "players": [
-            { "id": "p1", "mark": "mark_x" },
-            { "id": "p2", "mark": "mark_o" }
-        ],
-        "turn": "p1"
-    })
+/// What a registry entry expects for one argument.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgType {
+    /// `actor`, a seat like `p2`, or `$param`
+    Player,
+    /// a zone declared in the rules (or the implicit bank)
+    Zone,
+    /// an entity template id or instance id
+    Entity,
+    Int,
+    Bool,
+    /// like `Player`, or `random`
+    PlayerOrRandom,
+    /// a param type, as under a verb's `params`
+    Choice,
+    Text,
+    /// a hook registered for `on_answer`
+    AnswerHook,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Param {
+    pub name: &'static str,
+    pub ty: ArgType,
+    pub required: bool,
+}
+
+const fn req(name: &'static str, ty: ArgType) -> Param {
+    Param { name, ty, required: true }
+}
+
+const fn opt(name: &'static str, ty: ArgType) -> Param {
+    Param { name, ty, required: false }
+}
+
+/// Arguments of one call after substitution.
+pub struct Args {
+    actor: Option<String>,
+    values: Map<String, Value>,
+}
+
+impl Args {
+    fn opt_str(&self, key: &str) -> anyhow::Result<Option<&str>> {
+        match self.values.get(key) {
+            None => Ok(None),
+            Some(Value::String(s)) => Ok(Some(s)),
+            Some(other) => bail!("`{}` must be a string, got {}", key, other),
+        }
+    }
+
+    fn str(&self, key: &str) -> anyhow::Result<&str> {
+        self.opt_str(key)?.ok_or_else(|| anyhow!("missing `{}`", key))
+    }
+
+    fn opt_int(&self, key: &str) -> anyhow::Result<Option<i64>> {
+        match self.values.get(key) {
+            None => Ok(None),
+            Some(v) => v.as_i64().map(Some).ok_or_else(|| anyhow!("`{}` must be an integer, got {}", key, v)),
+        }
+    }
+
+    fn int(&self, key: &str) -> anyhow::Result<i64> {
+        self.opt_int(key)?.ok_or_else(|| anyhow!("missing `{}`", key))
+    }
+
+    fn bool(&self, key: &str) -> anyhow::Result<bool> {
+        match self.values.get(key) {
+            Some(Value::Bool(b)) => Ok(*b),
+            Some(other) => bail!("`{}` must be true or false, got {}", key, other),
+            None => bail!("missing `{}`", key),
+        }
+    }
+
+    fn opt_player(&self, key: &str) -> anyhow::Result<Option<&str>> {
+        self.opt_str(key)
+    }
+
+    fn player(&self, key: &str) -> anyhow::Result<&str> {
+        self.str(key)
+    }
+}
+
+/// A problem with one call found when the bundle loads.
+#[derive(Debug)]
+pub struct ArgError {
+    /// offending argument, or `None` when the call itself is wrong
+    pub arg: Option<String>,
+    pub message: String,
+}
+
+/// Check a `pre:` entry against the predicate registry: known name, required
+/// arguments present, no unknown ones, and literal values of the right type.
+/// `$param` references must name one of the verb's params.
+pub fn check_precondition(call: &Call, rules: &Rules, verb: &VerbTemplate) -> Result<(), ArgError> {
+    let predicate = predicates::lookup(&call.name).ok_or_else(|| ArgError {
+        arg: None,
+        message: format!(
+            "unknown precondition `{}` (known: {})",
+            call.name,
+            predicates::PREDICATES.iter().map(|p| p.name).collect::<Vec<_>>().join(", ")
+        ),
+    })?;
+    check_args(predicate.params, call, rules, Some(verb), &[])
+}
+
+/// Same checks for an `effect:` entry or a setup step (`verb` is `None` in setup).
+/// Here `$name` may also be an answer the verb binds with `ask: { as: name }`.
+pub fn check_effect(call: &Call, rules: &Rules, verb: Option<&VerbTemplate>) -> Result<(), ArgError> {
+    let effect = effects::lookup(&call.name).ok_or_else(|| ArgError {
+        arg: None,
+        message: format!(
+            "unknown effect `{}` (known: {})",
+            call.name,
+            effects::EFFECTS.iter().map(|e| e.name).collect::<Vec<_>>().join(", ")
+        ),
+    })?;
+    let answers: Vec<&str> = verb
+        .map(|v| v.effect.iter().filter(|e| e.name == "ask").filter_map(|e| e.args.get("as")?.as_str()).collect())
+        .unwrap_or_default();
+    check_args(effect.params, call, rules, verb, &answers)
 }
 
-pub fn apply_verb(_bundle: &Bundle, state: &mut serde_json::Value, json: &serde_json::Value) -> serde_json::Value {
-    // Check if we have a "place" verb
-    if json["verb"] == "place" {
-        // Extract parameters
-        if let (Some(row), Some(col)) = (json["args"]["row"].as_u64(), json["args"]["col"].as_u64()) {
-            // Convert to usize
-            let row = row as usize;
-            let col = col as usize;
-            
-            // Make sure they're within bounds (0-2)
-            if row <= 2 && col <= 2 {
-                // Get the current turn player - extract to a String to break the borrow
-                let current_player = state["turn"].as_str().unwrap_or("p1").to_string();
-                let mark = if current_player == "p1" { "mark_x" } else { "mark_o" };
-                
-                // Calculate the next player now
-                let next_player = if current_player == "p1" { "p2" } else { "p1" };
-                
-                // Update the board
-                if let Some(board) = state["zones"]["board"].as_array_mut() {
-                    if let Some(row_arr) = board.get_mut(row) {
-                        if let Some(cell) = row_arr.as_array_mut() {
-                            if cell[col].is_null() {
-                                cell[col] = serde_json::json!(mark);
-                                
-                                // Update the turn
-                                state["turn"] = serde_json::json!(next_player);
-                                
-                                // Return the diff for the cell update
-                                return serde_json::json!([
-                                    {
-                                        "op": "replace",
-                                        "path": format!("/zones/board/{}/{}", row, col),
-                                        "value": mark
-                                    },
-                                    {
-                                        "op": "replace",
-                                        "path": "/turn",
-                                        "value": next_player
-                                    }
-                                ]);
-                            }
-                        }
+fn check_args(
+    params: &[Param],
+    call: &Call,
+    rules: &Rules,
+    verb: Option<&VerbTemplate>,
+    answers: &[&str],
+) -> Result<(), ArgError> {
+    let err = |arg: &str, message: String| ArgError { arg: Some(arg.to_string()), message };
+    if let Some(unknown) = call.args.keys().find(|k| !params.iter().any(|p| p.name == k.as_str())) {
+        let expected: Vec<&str> = params.iter().map(|p| p.name).collect();
+        return Err(err(unknown, format!("`{}` takes no argument `{}` (expected: {})", call.name, unknown, expected.join(", "))));
+    }
+    for param in params {
+        let Some(value) = call.args.get(param.name) else {
+            if param.required {
+                return Err(ArgError { arg: None, message: format!("`{}` needs argument `{}`", call.name, param.name) });
+            }
+            continue;
+        };
+        if let Some(reference) = value.as_str().and_then(|s| s.strip_prefix('$')) {
+            match verb.and_then(|v| v.params.get(reference)) {
+                Some(spec) => {
+                    let fits = match param.ty {
+                        ArgType::Player | ArgType::PlayerOrRandom => spec.ty == ParamType::PlayerId,
+                        ArgType::Zone => matches!(spec.ty, ParamType::String | ParamType::Enum(_)),
+                        ArgType::Entity => matches!(spec.ty, ParamType::Id { .. } | ParamType::String | ParamType::Enum(_)),
+                        ArgType::Int => matches!(spec.ty, ParamType::Int { .. }),
+                        ArgType::Bool => spec.ty == ParamType::Bool,
+                        ArgType::Text => matches!(spec.ty, ParamType::String | ParamType::Enum(_)),
+                        ArgType::Choice | ArgType::AnswerHook => false,
+                    };
+                    if !fits {
+                        let declared = json!(spec);
+                        let declared = declared.as_str().map_or_else(|| declared.to_string(), str::to_string);
+                        return Err(err(param.name, format!("`${}` is declared `{}`, which `{}` can't take here", reference, declared, call.name)));
                     }
                 }
+                None if actor_attribute(reference).is_none() && !answers.contains(&reference) => {
+                    return Err(err(param.name, format!("`${}` is not a parameter of this verb", reference)));
+                }
+                None => {}
             }
+            continue;
+        }
+        let ok = match param.ty {
+            ArgType::Player => value.as_str().is_some_and(|s| s == "actor" || is_seat(s)),
+            ArgType::Zone => value.as_str().is_some_and(|s| s == BANK || rules.zones.contains_key(s)),
+            ArgType::Entity => value.as_str().is_some_and(|s| rules.entities.contains_key(s)),
+            ArgType::Int => value.is_i64(),
+            ArgType::Bool => value.is_boolean(),
+            ArgType::PlayerOrRandom => value.as_str().is_some_and(|s| s == "random" || s == "actor" || is_seat(s)),
+            ArgType::Choice => serde_json::from_value::<ParamSpec>(value.clone()).is_ok_and(|spec| match spec.ty {
+                ParamType::Id { zone: Some(zone) } => zone == BANK || rules.zones.contains_key(&zone),
+                _ => true,
+            }),
+            ArgType::Text => value.is_string(),
+            ArgType::AnswerHook => value.as_str().is_some_and(|s| rules.hooks.get(s).is_some_and(|h| h.on == HookEvent::Answer)),
+        };
+        if !ok {
+            let expected = match param.ty {
+                ArgType::Player => "a player (`actor`, a seat like `p1`, or `$param`)",
+                ArgType::Zone => "a declared zone",
+                ArgType::Entity => "a declared entity",
+                ArgType::Int => "an integer",
+                ArgType::Bool => "true or false",
+                ArgType::PlayerOrRandom => "a player or `random`",
+                ArgType::Choice => "a param type (`PlayerId`, `bool`, `[a, b]`, `{ type: Id, zone: hands }`, …)",
+                ArgType::Text => "a string",
+                ArgType::AnswerHook => "a hook registered for `on_answer`",
+            };
+            return Err(err(param.name, format!("expected {}, got {}", expected, value)));
+        }
+    }
+    Ok(())
+}
+
+/* --------------------------------------------------------------------------
+   helpers
+   ----------------------------------------------------------------------- */
+
+/// `actorMark` → `mark`: `$actor<Attr>` reads an attribute of the acting player.
+fn actor_attribute(name: &str) -> Option<String> {
+    name.strip_prefix("actor").filter(|a| !a.is_empty()).map(lower_first)
+}
+
+fn is_seat(s: &str) -> bool {
+    s.strip_prefix('p').is_some_and(|n| n.parse::<u32>().is_ok_and(|n| n > 0))
+}
+
+/// Every zone of `rules` as it is before anything is dealt, plus the bank.
+fn empty_zones(rules: &Rules, seats: &[String]) -> Map<String, Value> {
+    let mut zones = Map::new();
+    for (id, zone) in &rules.zones {
+        let empty = empty_zone(zone);
+        let value = if zone.per_player {
+            Value::Object(seats.iter().map(|s| (s.clone(), empty.clone())).collect())
+        } else {
+            empty
+        };
+        zones.insert(id.clone(), value);
+    }
+    zones.entry(BANK).or_insert_with(|| json!([]));
+    zones
+}
+
+fn empty_zone(zone: &ZoneTemplate) -> Value {
+    match zone.shape {
+        ZoneShape::Grid => {
+            let (w, h) = (zone.width.unwrap_or(0) as usize, zone.height.unwrap_or(0) as usize);
+            json!(vec![vec![Value::Null; w]; h])
         }
+        ZoneShape::Flag => json!(false),
+        _ => json!([]),
+    }
+}
+
+/// Does any `initZone` step (at any nesting depth) create `template`?
+fn places_entity(steps: &[SetupStep], template: &str) -> bool {
+    steps.iter().any(|step| match step {
+        SetupStep::Repeat { actions, .. } | SetupStep::ForEachPlayer(actions) => places_entity(actions, template),
+        SetupStep::Effect(call) => call.name == "initZone" && call.args.get("entity").and_then(Value::as_str) == Some(template),
+        SetupStep::Verb(_) => false,
+    })
+}
+
+fn lower_first(s: &str) -> String {
+    let mut chars = s.chars();
+    chars.next().map(|c| c.to_lowercase().chain(chars).collect()).unwrap_or_default()
+}
+
+/// A match of one of the repo's `games/` bundles, for unit tests.
+#[cfg(test)]
+fn test_match(game_id: &str, players: usize, seed: u64) -> Match {
+    let games = concat!(env!("CARGO_MANIFEST_DIR"), "/../games");
+    let bundle = crate::bundle::BundleMap::load_dir(games, crate::bundle::HashPolicy::Strict)
+        .expect("games directory loads")
+        .get_latest(game_id)
+        .expect("bundle is in games/");
+    let names: Vec<String> = (1..=players).map(|n| format!("user{}", n)).collect();
+    Match::new(bundle, &names, seed).expect("match sets up")
+}
+
+/// A match of a scratch `demo` bundle built from `entities` (the text of its
+/// `entities.yaml`), for unit tests.
+#[cfg(test)]
+fn scratch_match(entities: &str, players: usize, seed: u64) -> Match {
+    let names: Vec<String> = (1..=players).map(|n| format!("user{}", n)).collect();
+    Match::new(scratch_bundle(entities, None), &names, seed).expect("match sets up")
+}
+
+/// Load `entities` (and `script`, the bytes of a `script.wasm`) as bundle
+/// `demo` 1.0 for 1–6 players.
+#[cfg(test)]
+pub(crate) fn scratch_bundle(entities: &str, script: Option<&[u8]>) -> Arc<Bundle> {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+    let root = std::env::temp_dir().join(format!(
+        "bluefelt-scratch-{}-{}",
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    ));
+    let dir = root.join("demo").join("1.0");
+    std::fs::create_dir_all(&dir).unwrap();
+    let manifest = "gameId: demo\nversion: 1.0.0\nspecVersion: \"1\"\nmetadata:\n  name: Demo\n  players: { min: 1, max: 6 }\n";
+    std::fs::write(dir.join("manifest.yaml"), manifest).unwrap();
+    std::fs::write(dir.join("entities.yaml"), entities).unwrap();
+    if let Some(wasm) = script {
+        std::fs::write(dir.join("script.wasm"), wasm).unwrap();
     }
-    
-    // Default empty diff if no valid move was made
-    serde_json::json!([])
+    let bundles = crate::bundle::BundleMap::load_dir(root.to_str().unwrap(), crate::bundle::HashPolicy::Strict).unwrap();
+    std::fs::remove_dir_all(&root).unwrap();
+    bundles
+        .get_latest("demo")
+        .unwrap_or_else(|| panic!("scratch bundle does not load: {:?}", bundles.report().errors))
 }
@@ -0,0 +1,380 @@
+//! effects.rs – built-in `effect:` / `setup:` primitives
+//! Effects only change state through `Match::add/remove/replace`, so every
+//! mutation shows up in the tick's JSON-Patch diff.
+
+use super::{opt, pending::Question, req, Args, ArgType, Match, Param, BANK, ELIMINATED};
+use anyhow::{anyhow, bail};
+use serde_json::json;
+
+pub struct Effect {
+    pub name: &'static str,
+    pub params: &'static [Param],
+    pub run: fn(&mut Match, &Args) -> anyhow::Result<()>,
+}
+
+/// The registry. New effects only need an entry here.
+pub static EFFECTS: &[Effect] = &[
+    Effect {
+        name: "move",
+        params: &[
+            req("from", ArgType::Zone),
+            req("to", ArgType::Zone),
+            opt("count", ArgType::Int),
+            opt("entity", ArgType::Entity),
+            opt("playerSlot", ArgType::Player),
+            opt("row", ArgType::Int),
+            opt("col", ArgType::Int),
+        ],
+        run: move_entities,
+    },
+    Effect { name: "shuffle", params: &[req("zone", ArgType::Zone), opt("player", ArgType::Player)], run: shuffle },
+    Effect { name: "setTurn", params: &[req("player", ArgType::PlayerOrRandom)], run: set_turn },
+    Effect { name: "assignPiece", params: &[req("player", ArgType::Player), req("mark", ArgType::Entity)], run: assign_piece },
+    Effect {
+        name: "initZone",
+        params: &[req("entity", ArgType::Entity), opt("zone", ArgType::Zone), opt("count", ArgType::Int)],
+        run: init_zone,
+    },
+    Effect {
+        name: "setFlag",
+        params: &[req("zone", ArgType::Zone), opt("player", ArgType::Player), req("value", ArgType::Bool)],
+        run: set_flag,
+    },
+    Effect { name: "eliminate", params: &[req("player", ArgType::Player), opt("zone", ArgType::Zone)], run: eliminate },
+    Effect {
+        name: "eliminateIf",
+        params: &[
+            req("player", ArgType::Player),
+            req("holds", ArgType::Entity),
+            opt("in", ArgType::Zone),
+            opt("zone", ArgType::Zone),
+        ],
+        run: eliminate_if,
+    },
+    Effect {
+        name: "ask",
+        params: &[
+            req("player", ArgType::Player),
+            req("choice", ArgType::Choice),
+            opt("prompt", ArgType::Text),
+            opt("as", ArgType::Text),
+            opt("then", ArgType::AnswerHook),
+        ],
+        run: ask,
+    },
+];
+
+pub fn lookup(name: &str) -> Option<&'static Effect> {
+    EFFECTS.iter().find(|e| e.name == name)
+}
+
+/* --------------------------------------------------------------------------
+   primitives
+   ----------------------------------------------------------------------- */
+
+/// `count` entities from `from` to `to`: the named `entity`, else the top/front one.
+/// `playerSlot` picks the slot of per-player zones; `row`/`col` the cell of a grid.
+/// Entities moving into a zone that isn't public get fresh handles.
+fn move_entities(m: &mut Match, a: &Args) -> anyhow::Result<()> {
+    let (from, to) = (a.str("from")?, a.str("to")?);
+    let entity = a.opt_str("entity")?;
+    let slot = a.opt_player("playerSlot")?;
+    let cell = match (a.opt_int("row")?, a.opt_int("col")?) {
+        (Some(r), Some(c)) => Some((r as usize, c as usize)),
+        _ => None,
+    };
+    for _ in 0..a.opt_int("count")?.unwrap_or(1) {
+        let mut id = m.take(from, slot, entity)?;
+        if !m.is_public(to) {
+            id = m.rekey(&id)?;
+        }
+        m.put(to, slot, id, cell)?;
+    }
+    Ok(())
+}
+
+/// Fisher–Yates over a list zone, emitted as one `replace` of the zone. A zone
+/// that isn't public is re-keyed too, or the shuffle would hide nothing.
+fn shuffle(m: &mut Match, a: &Args) -> anyhow::Result<()> {
+    let zone = a.str("zone")?;
+    let pointer = m.zone_pointer(zone, a.opt_player("player")?)?;
+    let mut items = m.state.pointer(&pointer).and_then(|v| v.as_array()).cloned().unwrap_or_default();
+    for i in (1..items.len()).rev() {
+        items.swap(i, m.rng.below(i as u64 + 1) as usize);
+    }
+    if !m.is_public(zone) {
+        for item in items.iter_mut() {
+            if let Some(id) = item.as_str() {
+                *item = json!(m.rekey(id)?);
+            }
+        }
+    }
+    m.replace(pointer, json!(items))
+}
+
+fn set_turn(m: &mut Match, a: &Args) -> anyhow::Result<()> {
+    let seats = m.seats();
+    let player = match a.player("player")? {
+        "random" => seats.get(m.rng.below(seats.len() as u64) as usize).cloned().unwrap_or_default(),
+        p => p.to_string(),
+    };
+    if !seats.contains(&player) {
+        bail!("setTurn: unknown player `{}`", player);
+    }
+    m.replace("/turn/player".to_string(), json!(player))
+}
+
+/// Record which entity template a player places (`$actorMark`).
+fn assign_piece(m: &mut Match, a: &Args) -> anyhow::Result<()> {
+    let (player, mark) = (a.player("player")?, a.str("mark")?);
+    let index = m.seat_index(player).ok_or_else(|| anyhow!("assignPiece: unknown player `{}`", player))?;
+    m.add(format!("/players/{}/mark", index), json!(mark))
+}
+
+/// Create `count` fresh instances of `entity` in `zone` (the bank by default).
+fn init_zone(m: &mut Match, a: &Args) -> anyhow::Result<()> {
+    let entity = a.str("entity")?;
+    let zone = a.opt_str("zone")?.unwrap_or(BANK);
+    for _ in 0..a.opt_int("count")?.unwrap_or(1) {
+        m.spawn(entity, zone, None)?;
+    }
+    Ok(())
+}
+
+fn set_flag(m: &mut Match, a: &Args) -> anyhow::Result<()> {
+    let (zone, player) = (a.str("zone")?, a.opt_player("player")?);
+    let value = a.bool("value")?;
+    m.set_flag(zone, player, value)
+}
+
+fn eliminate(m: &mut Match, a: &Args) -> anyhow::Result<()> {
+    let player = a.player("player")?;
+    m.set_flag(a.opt_str("zone")?.unwrap_or(ELIMINATED), Some(player), true)
+}
+
+/// Eliminate `player` if they hold `holds` (template or id) in `in`
+/// (default: the first per-player list zone, e.g. `hands`).
+fn eliminate_if(m: &mut Match, a: &Args) -> anyhow::Result<()> {
+    let (player, entity) = (a.player("player")?, a.str("holds")?);
+    let hand = match a.opt_str("in")? {
+        Some(zone) => zone.to_string(),
+        None => m.hand_zone().ok_or_else(|| anyhow!("eliminateIf: no per-player zone to look in"))?,
+    };
+    if m.find_in(m.zone(&hand, Some(player))?, entity).is_some() {
+        m.set_flag(a.opt_str("zone")?.unwrap_or(ELIMINATED), Some(player), true)?;
+    }
+    Ok(())
+}
+
+/// Put a question to `player` and hold the rest of the verb until they answer
+/// (see pending.rs). `as` makes the answer `$as` for the effects after this one.
+fn ask(m: &mut Match, a: &Args) -> anyhow::Result<()> {
+    let choice = a.values.get("choice").cloned().ok_or_else(|| anyhow!("missing `choice`"))?;
+    m.ask(Question {
+        player: a.player("player")?.to_string(),
+        choice: serde_json::from_value(choice)?,
+        prompt: a.opt_str("prompt")?.map(str::to_string),
+        then: a.opt_str("then")?.map(str::to_string),
+        bind: a.opt_str("as")?.map(str::to_string),
+        context: serde_json::Value::Null,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::scratch_match;
+    use serde_json::{json, Value};
+
+    const RULES: &str = "
+entities:
+  guard: { kind: card }
+  priest: { kind: card }
+  chip: { kind: token }
+zones:
+  deck: { shape: stack, visibility: none }
+  pile: { shape: list, visibility: all }
+  discard: { shape: stack, visibility: all }
+  sealed: { shape: list, visibility: all, mutable: false }
+  hands: { shape: list, perPlayer: true, visibility: owner }
+  board: { shape: grid, width: 2, height: 2, visibility: all }
+  immunes: { shape: flag, perPlayer: true, visibility: all }
+  eliminated: { shape: flag, perPlayer: true, visibility: all }
+phases:
+  - id: turn
+    activePlayer: sequential
+setup:
+  - initZone: { zone: deck, entity: guard, count: 3 }
+  - initZone: { zone: pile, entity: chip, count: 4 }
+  - initZone: { zone: deck, entity: priest }
+  - move: { from: deck, to: hands, playerSlot: p1, entity: priest }
+";
+
+    /// Three seats: three guards in the deck, four chips in the pile, and
+    /// the priest in p1's hand.
+    fn table() -> Match {
+        scratch_match(RULES, 3, 1)
+    }
+
+    /// Run effect `name` with `args` as p1 would.
+    fn run(m: &mut Match, name: &str, args: Value) -> Result<(), String> {
+        let args = Args { actor: Some("p1".to_string()), values: args.as_object().unwrap().clone() };
+        (lookup(name).unwrap().run)(m, &args).map_err(|e| e.to_string())
+    }
+
+    fn zone(m: &Match, pointer: &str) -> Vec<String> {
+        let ids = m.state.pointer(&format!("/zones/{}", pointer)).and_then(Value::as_array).unwrap();
+        ids.iter().map(|id| id.as_str().unwrap().to_string()).collect()
+    }
+
+    #[test]
+    fn move_takes_the_front_of_a_pile_and_puts_it_on_top_of_a_stack() {
+        let mut m = table();
+        let top = zone(&m, "pile")[0].clone();
+        run(&mut m, "move", json!({ "from": "pile", "to": "discard" })).unwrap();
+        run(&mut m, "move", json!({ "from": "pile", "to": "discard" })).unwrap();
+        let second = zone(&m, "discard")[0].clone();
+        assert_eq!(zone(&m, "discard"), vec![second.clone(), top.clone()], "stacks grow at index 0");
+        assert_eq!(zone(&m, "pile").len(), 2);
+        assert_eq!(m.patch[0], json!({ "op": "remove", "path": "/zones/pile/0" }));
+        assert_eq!(m.patch[1], json!({ "op": "add", "path": "/zones/discard/0", "value": top }));
+    }
+
+    #[test]
+    fn move_into_a_zone_that_is_not_public_rekeys_the_entity() {
+        let mut m = table();
+        let chip = zone(&m, "pile")[0].clone();
+        run(&mut m, "move", json!({ "from": "pile", "to": "hands", "playerSlot": "p2" })).unwrap();
+        let [fresh] = &zone(&m, "hands/p2")[..] else { panic!("one entity in p2's hand") };
+        assert_ne!(*fresh, chip);
+        assert!(m.state["entities"].get(&chip).is_none());
+        assert_eq!(m.template_of(fresh), Some("chip"));
+    }
+
+    #[test]
+    fn move_picks_a_named_entity_and_repeats_for_count() {
+        let mut m = table();
+        run(&mut m, "move", json!({ "from": "hands", "to": "discard", "playerSlot": "p1", "entity": "priest" })).unwrap();
+        assert_eq!(m.template_of(&zone(&m, "discard")[0]), Some("priest"));
+        run(&mut m, "move", json!({ "from": "pile", "to": "discard", "count": 3 })).unwrap();
+        assert_eq!((zone(&m, "pile").len(), zone(&m, "discard").len()), (1, 4));
+        assert_eq!(
+            run(&mut m, "move", json!({ "from": "pile", "to": "discard", "entity": "guard" })),
+            Err("`guard` is not in `pile`".into())
+        );
+    }
+
+    #[test]
+    fn move_onto_a_grid_needs_a_free_cell() {
+        let mut m = table();
+        run(&mut m, "move", json!({ "from": "pile", "to": "board", "row": 1, "col": 0 })).unwrap();
+        assert!(m.state["zones"]["board"][1][0].is_string());
+        assert_eq!(
+            run(&mut m, "move", json!({ "from": "pile", "to": "board", "row": 1, "col": 0 })),
+            Err("cell 1,0 of `board` is occupied".into())
+        );
+        assert_eq!(
+            run(&mut m, "move", json!({ "from": "pile", "to": "board", "row": 2, "col": 0 })),
+            Err("cell 2,0 is off the grid".into())
+        );
+        assert_eq!(
+            run(&mut m, "move", json!({ "from": "pile", "to": "board" })),
+            Err("moving into grid `board` needs row and col".into())
+        );
+    }
+
+    #[test]
+    fn move_refuses_empty_and_immutable_zones() {
+        let mut m = table();
+        assert_eq!(run(&mut m, "move", json!({ "from": "discard", "to": "pile" })), Err("zone `discard` is empty".into()));
+        assert_eq!(run(&mut m, "move", json!({ "from": "pile", "to": "sealed" })), Err("zone `sealed` is not mutable".into()));
+    }
+
+    #[test]
+    fn shuffle_permutes_a_zone_in_one_replace() {
+        let mut m = table();
+        let mut before = zone(&m, "pile");
+        run(&mut m, "shuffle", json!({ "zone": "pile" })).unwrap();
+        let [op] = &m.patch[..] else { panic!("one op, got {:?}", m.patch) };
+        assert_eq!((&op["op"], &op["path"]), (&json!("replace"), &json!("/zones/pile")));
+        let mut after = zone(&m, "pile");
+        before.sort();
+        after.sort();
+        assert_eq!(after, before, "a public zone keeps its ids");
+    }
+
+    #[test]
+    fn shuffling_a_hidden_zone_rekeys_every_entity() {
+        let mut m = table();
+        let before = zone(&m, "deck");
+        run(&mut m, "shuffle", json!({ "zone": "deck" })).unwrap();
+        let after = zone(&m, "deck");
+        assert!(after.iter().all(|id| !before.contains(id)));
+        assert!(after.iter().all(|id| m.template_of(id) == Some("guard")));
+    }
+
+    #[test]
+    fn set_turn_takes_a_seat_or_a_random_one() {
+        let mut m = table();
+        run(&mut m, "setTurn", json!({ "player": "p3" })).unwrap();
+        assert_eq!(m.turn_player(), Some("p3"));
+        run(&mut m, "setTurn", json!({ "player": "random" })).unwrap();
+        assert!(m.seats().iter().any(|s| Some(s.as_str()) == m.turn_player()));
+        assert_eq!(run(&mut m, "setTurn", json!({ "player": "p7" })), Err("setTurn: unknown player `p7`".into()));
+    }
+
+    #[test]
+    fn assign_piece_records_the_players_mark() {
+        let mut m = table();
+        run(&mut m, "assignPiece", json!({ "player": "p2", "mark": "chip" })).unwrap();
+        assert_eq!(m.state["players"][1]["mark"], "chip");
+        assert_eq!(m.patch, vec![json!({ "op": "add", "path": "/players/1/mark", "value": "chip" })]);
+    }
+
+    #[test]
+    fn init_zone_creates_instances_in_the_bank_by_default() {
+        let mut m = table();
+        run(&mut m, "initZone", json!({ "entity": "guard", "count": 2 })).unwrap();
+        let bank = zone(&m, BANK);
+        assert_eq!(bank.len(), 2);
+        assert!(bank.iter().all(|id| m.template_of(id) == Some("guard")));
+        assert_eq!(run(&mut m, "initZone", json!({ "entity": "baron" })), Err("unknown entity `baron`".into()));
+    }
+
+    #[test]
+    fn set_flag_and_eliminate_flip_per_player_flags() {
+        let mut m = table();
+        run(&mut m, "setFlag", json!({ "zone": "immunes", "player": "p2", "value": true })).unwrap();
+        run(&mut m, "eliminate", json!({ "player": "p3" })).unwrap();
+        assert_eq!(m.state["zones"]["immunes"], json!({ "p1": false, "p2": true, "p3": false }));
+        assert_eq!(m.state["zones"]["eliminated"], json!({ "p1": false, "p2": false, "p3": true }));
+
+        m.patch.clear();
+        run(&mut m, "setFlag", json!({ "zone": "immunes", "player": "p2", "value": true })).unwrap();
+        assert!(m.patch.is_empty(), "setting a flag to its value records nothing");
+        assert_eq!(
+            run(&mut m, "setFlag", json!({ "zone": "pile", "value": true })),
+            Err("`pile` is not a flag zone".into())
+        );
+    }
+
+    #[test]
+    fn eliminate_if_only_eliminates_a_holder() {
+        let mut m = table();
+        run(&mut m, "eliminateIf", json!({ "player": "p1", "holds": "priest" })).unwrap();
+        run(&mut m, "eliminateIf", json!({ "player": "p2", "holds": "priest" })).unwrap();
+        assert_eq!(m.state["zones"]["eliminated"], json!({ "p1": true, "p2": false, "p3": false }));
+    }
+
+    #[test]
+    fn ask_opens_a_question_for_the_player() {
+        let mut m = table();
+        run(&mut m, "ask", json!({ "player": "p2", "choice": "PlayerId", "prompt": "Who?" })).unwrap();
+        assert_eq!(m.state["pending"]["player"], "p2");
+        assert_eq!(m.state["pending"]["prompt"], "Who?");
+        assert_eq!(
+            run(&mut m, "ask", json!({ "player": "p3", "choice": "bool" })),
+            Err("p2 has not answered the last question yet".into())
+        );
+    }
+}
@@ -0,0 +1,151 @@
+//! events.rs – lifecycle events and the bundle hooks subscribed to them
+//! Whatever a hook asks for (`emit`, `advance_turn`, `round_end`, `ask`) is applied
+//! through the recorded mutations, so it lands in the diff of the current tick.
+
+use super::Match;
+use crate::entities::HookEvent;
+use crate::hooks::{self, FailurePolicy};
+use anyhow::{anyhow, bail, Context};
+use serde_json::{json, Value};
+use std::fmt;
+
+/// A hook call failed and the server's policy is to abort the tick.
+#[derive(Debug)]
+pub struct HookFailure(pub anyhow::Error);
+
+impl fmt::Display for HookFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#}", self.0)
+    }
+}
+
+impl std::error::Error for HookFailure {}
+
+impl Match {
+    /// Call the hooks registered for `event`, in registry order and starting at
+    /// registry index `from`: the ones no entity names in its `hook:` field always,
+    /// and `entity`'s own hook if it has one. Each gets `{ event, phase, actor, verb,
+    /// args, entity }`. When one of them asks a question, the rest wait: the index
+    /// to carry on from is returned.
+    pub(super) fn fire(
+        &mut self,
+        event: HookEvent,
+        phase: &str,
+        entity: Option<&str>,
+        from: usize,
+    ) -> anyhow::Result<Option<usize>> {
+        if self.hooks.is_none() {
+            return self.check_hooks_alive().map(|()| None);
+        }
+        let bundle = self.bundle.clone();
+        let own_hook = entity
+            .and_then(|id| self.template_of(id))
+            .and_then(|t| bundle.rules.entities.get(t))
+            .and_then(|t| t.hook.as_deref());
+        let payload = json!({
+            "event": event,
+            "phase": phase,
+            "actor": self.invocation["actor"],
+            "verb": self.invocation["verb"],
+            "args": self.invocation["args"],
+            "entity": entity,
+        });
+        for (index, (name, _)) in bundle.rules.hooks.iter().enumerate().skip(from).filter(|(_, (_, h))| h.runs_on(event, phase)) {
+            let entity_hook = bundle.rules.entities.values().any(|t| t.hook.as_ref() == Some(name));
+            if entity_hook && own_hook != Some(name.as_str()) {
+                continue;
+            }
+            if self.round_decided() {
+                break;
+            }
+            if self.call_hook(name, &payload)? {
+                return Ok(Some(index + 1));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Run one hook and apply what it asked for; true when it asked a question.
+    pub(super) fn call_hook(&mut self, name: &str, payload: &Value) -> anyhow::Result<bool> {
+        let bundle = self.bundle.clone();
+        let Some(runtime) = self.hooks.as_mut() else { return self.check_hooks_alive().map(|()| false) };
+        let outcome = match runtime.call(name, payload, &self.state, &mut self.rng, &mut self.hook_budget) {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                println!("[Hooks] ERROR: {} {}: {:#}", bundle.game_id, bundle.version, e);
+                return match hooks::limits().on_failure {
+                    // the rollback puts back the instance as it was before the tick
+                    FailurePolicy::Abort => Err(HookFailure(e).into()),
+                    FailurePolicy::Skip => {
+                        // a trapped instance may be stuck half-way through anything; start over
+                        self.restart_hooks().map_err(HookFailure)?;
+                        Ok(false)
+                    }
+                };
+            }
+        };
+        for op in &outcome.ops {
+            self.apply_op(op)
+                .with_context(|| format!("hook `{}` emitted {}", name, op))
+                .map_err(HookFailure)?;
+        }
+        if let Some(winner) = outcome.round_end {
+            // the round is over; a question asked alongside has nothing left to decide
+            self.end_round(&winner)?;
+            return Ok(false);
+        }
+        if outcome.advance_turn {
+            self.advance_turn()?;
+        }
+        match outcome.ask {
+            Some(request) => {
+                self.ask_from_hook(&request).with_context(|| format!("hook `{}`", name)).map_err(HookFailure)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Replace the match's instance of `script.wasm` with a fresh one. If that
+    /// fails the match is left without one, and every later hook call fails.
+    fn restart_hooks(&mut self) -> anyhow::Result<()> {
+        let bundle = self.bundle.clone();
+        let Some(script) = bundle.script.as_ref() else { return Ok(()) };
+        match script.instantiate() {
+            Ok(runtime) => {
+                self.hooks = Some(runtime);
+                Ok(())
+            }
+            Err(e) => {
+                println!("[Hooks] ERROR: {} {}: cannot restart script.wasm: {:#}", bundle.game_id, bundle.version, e);
+                self.hooks = None;
+                Err(e.context("cannot restart script.wasm"))
+            }
+        }
+    }
+
+    /// Without an instance, hooks only go unrun when the bundle has no script;
+    /// a match that lost its instance refuses to carry on without its rules.
+    fn check_hooks_alive(&self) -> anyhow::Result<()> {
+        if self.bundle.script.is_some() {
+            return Err(HookFailure(anyhow!("script.wasm could not be restarted after a failed hook")).into());
+        }
+        Ok(())
+    }
+
+    /// The match has a `result`; no verb is accepted any more.
+    pub fn is_over(&self) -> bool {
+        self.state.get("result").is_some()
+    }
+
+    /// Re-apply a JSON-Patch op from a hook through the recorded mutations.
+    fn apply_op(&mut self, op: &Value) -> anyhow::Result<()> {
+        let path = op["path"].as_str().ok_or_else(|| anyhow!("patch op without a path"))?.to_string();
+        match op["op"].as_str() {
+            Some("add") => self.add(path, op["value"].clone()),
+            Some("remove") => self.remove(path).map(drop),
+            Some("replace") => self.replace(path, op["value"].clone()),
+            other => bail!("unsupported patch op {:?}", other.unwrap_or("")),
+        }
+    }
+}
@@ -0,0 +1,36 @@
+//! handles.rs – opaque entity ids
+//! An entity's id says nothing about what it is: it is a random `§` handle from
+//! the RNG's handle stream (see rng.rs), and only `entities[id].template` (sent
+//! to players allowed to see it) does. An
+//! entity gets a fresh handle whenever it lands somewhere not everyone can see,
+//! so a handle someone has learned can't be followed into a hand or a deck.
+
+use super::{escape, Match};
+use crate::entities::Visibility;
+use anyhow::anyhow;
+
+impl Match {
+    /// A handle no entity of this match uses.
+    pub(super) fn new_handle(&mut self) -> String {
+        loop {
+            let id = format!("§{:012x}", self.rng.next_handle());
+            if self.state["entities"].get(&id).is_none() {
+                return id;
+            }
+        }
+    }
+
+    /// Whether everyone sees which entities sit in `zone`.
+    pub(super) fn is_public(&self, zone: &str) -> bool {
+        self.bundle.rules.zones.get(zone).is_some_and(|z| z.visibility == Visibility::All)
+    }
+
+    /// Move entity `id` to a fresh handle and return it. Only the `entities`
+    /// entry changes; the caller puts the new id wherever the old one was going.
+    pub(super) fn rekey(&mut self, id: &str) -> anyhow::Result<String> {
+        let entity = self.remove(format!("/entities/{}", escape(id))).map_err(|_| anyhow!("unknown entity `{}`", id))?;
+        let fresh = self.new_handle();
+        self.add(format!("/entities/{}", escape(&fresh)), entity)?;
+        Ok(fresh)
+    }
+}
@@ -0,0 +1,194 @@
+//! legal.rs – the moves a seat can make right now
+//! Every verb of the current phase the seat may act in, with the argument
+//! combinations that pass its `pre:` checks. A param's candidates follow from
+//! its declared type: seats for `PlayerId`, the entities the seat can see (in
+//! the param's `zone`, if any) for `Id`, an integer's range or else the grid
+//! coordinates, the listed values of an enum. Optional params are also tried left out.
+
+use super::{Ctx, Match, ANSWER};
+use crate::entities::{ParamSpec, ParamType, VerbTemplate, ZoneShape};
+use indexmap::IndexMap;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+/// Combinations tried per verb; a verb with more is listed without `args`.
+const MAX_COMBINATIONS: usize = 4096;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LegalMove {
+    pub verb: String,
+    /// param name → declared type
+    pub params: IndexMap<String, ParamSpec>,
+    /// param name → the values it takes in some legal combination
+    pub domains: IndexMap<String, Vec<Value>>,
+    /// every legal combination; `None` when some param can't be enumerated
+    /// (free text, or too many combinations), so only phase and turn were checked
+    pub args: Option<Vec<Map<String, Value>>>,
+}
+
+impl Match {
+    /// What `seat` may play now; empty when it is not their turn or the game is over.
+    /// While a question is open, only the player asked has a move: `answer`.
+    pub fn legal_moves(&self, seat: &str) -> Vec<LegalMove> {
+        if self.is_over() {
+            return Vec::new();
+        }
+        if let Some(player) = self.pending_player() {
+            return match self.answer_verb() {
+                Some(answer) if player == seat => self.legal_move(ANSWER, &answer, seat).into_iter().collect(),
+                _ => Vec::new(),
+            };
+        }
+        let rules = &self.bundle.rules;
+        let Some(phase) = rules.phase(self.phase_id()) else { return Vec::new() };
+        if !self.active_players().iter().any(|p| p == seat) {
+            return Vec::new();
+        }
+        phase
+            .verbs
+            .iter()
+            .filter_map(|name| self.legal_move(name, rules.verbs.get(name)?, seat))
+            .collect()
+    }
+
+    /// `verb` as `seat` could send it now, or `None` when no combination of args passes.
+    pub(super) fn legal_move(&self, name: &str, verb: &VerbTemplate, seat: &str) -> Option<LegalMove> {
+        let visible: Vec<Value> = self.view(Some(seat))["entities"]
+            .as_object()
+            .map(|es| es.keys().map(|id| json!(id)).collect())
+            .unwrap_or_default();
+        // `None` in a list: the param is left out
+        let candidates: IndexMap<&String, Option<Vec<Option<Value>>>> = verb
+            .params
+            .iter()
+            .map(|(param, spec)| {
+                let values = self.candidates(&spec.ty, seat, &visible).map(|vs| {
+                    let absent = spec.optional.then_some(None);
+                    absent.into_iter().chain(vs.into_iter().map(Some)).collect()
+                });
+                (param, values)
+            })
+            .collect();
+        let count = candidates.values().try_fold(1usize, |n, c| n.checked_mul(c.as_ref()?.len()));
+
+        let args = count.filter(|n| *n <= MAX_COMBINATIONS).map(|count| {
+            let mut legal = Vec::new();
+            for mut index in 0..count {
+                let mut combination = Map::new();
+                for (param, values) in &candidates {
+                    let values = values.as_deref().unwrap_or_default();
+                    if let Some(value) = &values[index % values.len()] {
+                        combination.insert(param.to_string(), value.clone());
+                    }
+                    index /= values.len();
+                }
+                if self.check_pre(verb, &Ctx { actor: Some(seat), params: &combination }).is_ok() {
+                    legal.push(combination);
+                }
+            }
+            legal
+        });
+        if args.as_ref().is_some_and(Vec::is_empty) {
+            return None;
+        }
+        let domains = candidates
+            .into_iter()
+            .filter_map(|(param, values)| {
+                let mut values: Vec<Value> = values?.into_iter().flatten().collect();
+                if let Some(legal) = &args {
+                    values.retain(|v| legal.iter().any(|c| c.get(param.as_str()) == Some(v)));
+                }
+                Some((param.clone(), values))
+            })
+            .collect();
+        Some(LegalMove { verb: name.to_string(), params: verb.params.clone(), domains, args })
+    }
+
+    /// Values a param of type `ty` can take, or `None` when they can't be listed.
+    fn candidates(&self, ty: &ParamType, seat: &str, visible: &[Value]) -> Option<Vec<Value>> {
+        match ty {
+            ParamType::PlayerId => Some(self.seats().into_iter().map(Value::from).collect()),
+            ParamType::Id { zone: None } => Some(visible.to_vec()),
+            ParamType::Id { zone: Some(zone) } => {
+                let pile = self.zone(zone, Some(seat)).ok()?;
+                Some(visible.iter().filter(|id| id.as_str().is_some_and(|id| holds_id(pile, id))).cloned().collect())
+            }
+            ParamType::Bool => Some(vec![json!(false), json!(true)]),
+            ParamType::Enum(values) => Some(values.iter().map(|v| json!(v)).collect()),
+            ParamType::String => None,
+            ParamType::Int { min: None, max: None, .. } => {
+                // an integer without a declared range addresses grid cells
+                let bound = self
+                    .bundle
+                    .rules
+                    .zones
+                    .values()
+                    .filter(|z| z.shape == ZoneShape::Grid)
+                    .flat_map(|z| [z.width, z.height])
+                    .flatten()
+                    .max()?;
+                let (lo, hi) = ty.int_range()?;
+                Some((lo.max(0)..=hi.min(bound as i64 - 1)).map(Value::from).collect())
+            }
+            ParamType::Int { .. } => {
+                let (lo, hi) = ty.int_range()?;
+                let size = hi.checked_sub(lo)?.checked_add(1)?;
+                (size <= MAX_COMBINATIONS as i64).then(|| (lo..=hi).map(Value::from).collect())
+            }
+        }
+    }
+}
+
+/// Whether `id` sits in `pile`, at any depth (grids are lists of rows).
+pub(super) fn holds_id(pile: &Value, id: &str) -> bool {
+    match pile {
+        Value::String(s) => s == id,
+        Value::Array(items) => items.iter().any(|item| holds_id(item, id)),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::test_match;
+
+    fn cells(moves: &[LegalMove]) -> Vec<(i64, i64)> {
+        let [place] = moves else { panic!("expected only `place`, got {:?}", moves) };
+        assert_eq!(place.verb, "place");
+        let args = place.args.as_ref().expect("a board's cells are enumerable");
+        let mut cells: Vec<_> = args.iter().map(|a| (a["row"].as_i64().unwrap(), a["col"].as_i64().unwrap())).collect();
+        cells.sort();
+        cells
+    }
+
+    fn place(game: &mut Match, seat: &str, row: u8, col: u8) {
+        game.apply_verb(seat, &json!({ "verb": "place", "args": { "row": row, "col": col } })).expect("legal move");
+    }
+
+    #[test]
+    fn tic_tac_toe_offers_every_empty_cell_to_the_player_on_turn() {
+        let mut game = test_match("tic-tac-toe", 2, 1);
+        let every: Vec<(i64, i64)> = (0..3).flat_map(|r| (0..3).map(move |c| (r, c))).collect();
+        assert_eq!(cells(&game.legal_moves("p1")), every);
+        assert!(game.legal_moves("p2").is_empty(), "not p2's turn");
+
+        place(&mut game, "p1", 1, 1);
+        assert!(game.legal_moves("p1").is_empty());
+        let open = cells(&game.legal_moves("p2"));
+        assert_eq!(open, every.iter().copied().filter(|cell| *cell != (1, 1)).collect::<Vec<_>>());
+        let domains = &game.legal_moves("p2")[0].domains;
+        assert_eq!(domains["row"], vec![json!(0), json!(1), json!(2)]);
+    }
+
+    #[test]
+    fn tic_tac_toe_has_no_moves_once_won() {
+        let mut game = test_match("tic-tac-toe", 2, 1);
+        for (seat, row, col) in [("p1", 0, 0), ("p2", 1, 0), ("p1", 0, 1), ("p2", 1, 1), ("p1", 0, 2)] {
+            place(&mut game, seat, row, col);
+        }
+        assert!(game.is_over());
+        assert!(game.legal_moves("p1").is_empty());
+        assert!(game.legal_moves("p2").is_empty());
+    }
+}
@@ -0,0 +1,201 @@
+//! log.rs – append-only record of a match
+//! The state after setup plus every accepted action, in order. Applying the
+//! logged diffs to the initial state rebuilds any tick; re-running the actions
+//! on a fresh match with the same seed must reproduce those diffs exactly.
+
+use super::{patch, rounds::RoundEnd, Match, State, Tick};
+use crate::bundle::Bundle;
+use anyhow::{anyhow, bail};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchLog {
+    pub game_id: String,
+    pub version: String,
+    pub bundle_hash: String,
+    pub seed: u64,
+    /// user names, in seat order
+    pub players: Vec<String>,
+    pub initial_state: State,
+    pub events: Vec<LogEvent>,
+}
+
+/// One accepted action and what it did.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogEvent {
+    pub tick: u64,
+    pub seq: u64,
+    pub actor: String,
+    pub verb: String,
+    pub args: Value,
+    /// the whole diff
+    pub diff: Value,
+    /// seat → the projection that seat was sent
+    pub diffs: IndexMap<String, Value>,
+    /// digest of the numbers drawn from the match RNG while applying it
+    pub rng_digest: String,
+    /// set when a hook ended the round during it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub round_end: Option<RoundEnd>,
+}
+
+impl LogEvent {
+    /// What `seat` was sent for it: the `event` envelope, then `roundEnd` and
+    /// `gameOver` if it ended the round or the match.
+    pub fn envelopes(&self, seat: &str) -> Vec<Value> {
+        let event = json!({
+            "type": "event",
+            "tick": self.tick,
+            "seq": self.seq,
+            "actor": self.actor,
+            "verb": self.verb,
+            "args": self.args,
+            "diff": self.diffs.get(seat),
+        });
+        let round_end = self.round_end.iter().flat_map(|end| end.envelopes(self.tick, self.seq));
+        std::iter::once(event).chain(round_end).collect()
+    }
+}
+
+impl MatchLog {
+    pub(super) fn start(m: &Match, players: &[String], seed: u64) -> Self {
+        Self {
+            game_id: m.bundle.game_id.clone(),
+            version: m.bundle.version.to_string(),
+            bundle_hash: m.bundle.hash.clone(),
+            seed,
+            players: players.to_vec(),
+            initial_state: m.state.clone(),
+            events: Vec::new(),
+        }
+    }
+
+    pub(super) fn record(&mut self, tick: &Tick) {
+        self.events.push(LogEvent {
+            tick: tick.tick,
+            seq: tick.seq,
+            actor: tick.actor.clone(),
+            verb: tick.verb.clone(),
+            args: tick.args.clone(),
+            diff: tick.diff.clone(),
+            diffs: tick.views.clone(),
+            rng_digest: tick.rng_digest.clone(),
+            round_end: tick.round_end.clone(),
+        });
+    }
+
+    /// The state as it was after `tick`, from the initial state and the logged diffs.
+    pub fn state_at(&self, tick: u64) -> anyhow::Result<State> {
+        let mut state = self.initial_state.clone();
+        for event in self.events.iter().take_while(|e| e.tick <= tick) {
+            for op in event.diff.as_array().into_iter().flatten() {
+                patch::apply(&mut state, op).map_err(|e| anyhow!("tick {}: {:#}", event.tick, e))?;
+            }
+        }
+        Ok(state)
+    }
+}
+
+impl Match {
+    /// Play a logged match again on `bundle` from its seed and actions, checking
+    /// every tick against the log.
+    pub fn replay(bundle: Arc<Bundle>, log: &MatchLog) -> anyhow::Result<Self> {
+        if bundle.hash != log.bundle_hash {
+            bail!("the log was recorded on build {}, not {}", log.bundle_hash, bundle.hash);
+        }
+        let mut m = Match::new(bundle, &log.players, log.seed)?;
+        if m.state != log.initial_state {
+            bail!("setup does not reproduce the logged initial state");
+        }
+        for event in &log.events {
+            let action = json!({ "verb": event.verb, "args": event.args });
+            let tick = m
+                .apply_verb(&event.actor, &action)
+                .map_err(|r| anyhow!("tick {}: `{}` from {} was rejected: {}", event.tick, event.verb, event.actor, r))?;
+            if tick.tick != event.tick || tick.diff != event.diff {
+                bail!("tick {}: `{}` from {} does not reproduce the logged diff", event.tick, event.verb, event.actor);
+            }
+            if tick.rng_digest != event.rng_digest {
+                bail!("tick {}: `{}` from {} does not draw the logged random numbers", event.tick, event.verb, event.actor);
+            }
+            if tick.round_end != event.round_end {
+                bail!("tick {}: `{}` from {} does not end the round as logged", event.tick, event.verb, event.actor);
+            }
+        }
+        Ok(m)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{scratch_match, Rng};
+
+    /// Every verb but `draw` draws random numbers: `mix` shuffles the deck
+    /// and `pass` gives the turn to a random seat.
+    const RULES: &str = "
+entities:
+  card: { kind: card }
+zones:
+  deck: { shape: stack, visibility: none }
+  hands: { shape: list, perPlayer: true, visibility: owner }
+verbs:
+  mix: { effect: [{ shuffle: { zone: deck } }] }
+  draw:
+    pre: [{ zoneNotEmpty: { zone: deck } }]
+    effect: [{ move: { from: deck, to: hands, count: 1, playerSlot: actor } }]
+  pass: { effect: [{ setTurn: { player: random } }] }
+phases:
+  - id: turn
+    activePlayer: sequential
+    verbs: [mix, draw, pass]
+setup:
+  - initZone: { zone: deck, entity: card, count: 8 }
+";
+
+    /// Play `steps` moves, each picked from the legal moves by a fixed
+    /// pattern, and return the state after every tick.
+    fn play_out(m: &mut Match, steps: usize) -> Vec<(u64, State)> {
+        let mut states = vec![(0, m.state.clone())];
+        for step in 0..steps {
+            let (seat, moves) =
+                m.seats().into_iter().map(|s| (s.clone(), m.legal_moves(&s))).find(|(_, moves)| !moves.is_empty()).unwrap();
+            let verb = &moves[step * 7 % moves.len()].verb;
+            let tick = m.apply_verb(&seat, &json!({ "verb": verb, "args": {} })).expect("legal move");
+            states.push((tick.tick, m.state.clone()));
+        }
+        states
+    }
+
+    #[test]
+    fn replay_and_state_at_rebuild_a_played_match() {
+        let mut m = scratch_match(RULES, 3, 7);
+        let states = play_out(&mut m, 40);
+        let nothing_drawn = Rng::default().tick_digest();
+        assert!(m.log.events.iter().filter(|e| e.rng_digest != nothing_drawn).count() > 10);
+
+        let replayed = Match::replay(m.bundle.clone(), &m.log).unwrap();
+        assert_eq!(replayed.state, m.state);
+        for (tick, state) in &states {
+            assert_eq!(&m.log.state_at(*tick).unwrap(), state, "state after tick {}", tick);
+        }
+    }
+
+    #[test]
+    fn replay_fails_where_the_random_numbers_differ() {
+        let mut m = scratch_match(RULES, 3, 7);
+        play_out(&mut m, 40);
+        let mut log = m.log.clone();
+        let nothing_drawn = Rng::default().tick_digest();
+        let drawing = log.events.iter().position(|e| e.rng_digest != nothing_drawn).unwrap();
+        log.events[drawing].rng_digest = nothing_drawn;
+        let error = Match::replay(m.bundle.clone(), &log).err().unwrap().to_string();
+        assert_eq!(error, format!("tick {}: `{}` from {} does not draw the logged random numbers",
+            log.events[drawing].tick, log.events[drawing].verb, log.events[drawing].actor));
+    }
+}
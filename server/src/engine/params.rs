@@ -0,0 +1,144 @@
+//! params.rs – a verb's `args` against its declared params
+//! Runs before any `pre:` check, so predicates and effects only ever see the
+//! declared params, each of its declared type.
+
+use super::{legal::holds_id, Match, RejectCode, Rejection};
+use crate::entities::{ParamSpec, ParamType, VerbTemplate};
+use anyhow::{anyhow, bail, ensure};
+use serde_json::{Map, Value};
+
+impl Match {
+    /// Reject missing or unexpected args and any value outside its param's type.
+    pub(super) fn check_params(&self, verb: &VerbTemplate, args: &Value, actor: &str) -> Result<(), Rejection> {
+        let invalid = |reason: String| Rejection::new(RejectCode::InvalidArgs, reason);
+        let empty = Map::new();
+        let args = match args {
+            Value::Object(args) => args,
+            Value::Null => &empty,
+            other => return Err(invalid(format!("`args` must be an object, got {}", other))),
+        };
+        if let Some(unexpected) = args.keys().find(|k| !verb.params.contains_key(*k)) {
+            let expected: Vec<&str> = verb.params.keys().map(String::as_str).collect();
+            let expected = if expected.is_empty() { "none".to_string() } else { expected.join(", ") };
+            return Err(invalid(format!("unexpected argument `{}` (expected: {})", unexpected, expected)));
+        }
+        for (name, spec) in &verb.params {
+            match args.get(name) {
+                None if spec.optional => {}
+                None => return Err(invalid(format!("missing argument `{}`", name))),
+                Some(value) => self.check_value(spec, value, actor).map_err(|e| invalid(format!("`{}`: {:#}", name, e)))?,
+            }
+        }
+        Ok(())
+    }
+
+    fn check_value(&self, spec: &ParamSpec, value: &Value, actor: &str) -> anyhow::Result<()> {
+        match &spec.ty {
+            ParamType::Int { name, .. } => {
+                let (lo, hi) = spec.ty.int_range().ok_or_else(|| anyhow!("unknown integer type `{}`", name))?;
+                let n = value.as_i64().ok_or_else(|| anyhow!("expected an integer, got {}", value))?;
+                ensure!((lo..=hi).contains(&n), "{} is outside {}..={}", n, lo, hi);
+            }
+            ParamType::Bool => ensure!(value.is_boolean(), "expected true or false, got {}", value),
+            ParamType::String => ensure!(value.is_string(), "expected a string, got {}", value),
+            ParamType::PlayerId => {
+                let seat = value.as_str().ok_or_else(|| anyhow!("expected a player id, got {}", value))?;
+                ensure!(self.seat_index(seat).is_some(), "no player `{}`", seat);
+            }
+            ParamType::Id { zone } => {
+                let id = value.as_str().ok_or_else(|| anyhow!("expected an entity id, got {}", value))?;
+                ensure!(self.template_of(id).is_some(), "no entity `{}`", id);
+                if let Some(zone) = zone {
+                    if !holds_id(self.zone(zone, Some(actor))?, id) {
+                        bail!("`{}` is not in `{}`", id, zone);
+                    }
+                }
+            }
+            ParamType::Enum(values) => {
+                let s = value.as_str().unwrap_or_default();
+                ensure!(values.iter().any(|v| v == s), "expected one of {}, got {}", values.join(", "), value);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::scratch_match;
+    use serde_json::json;
+
+    const RULES: &str = "
+entities:
+  card: { kind: card }
+zones:
+  deck: { shape: stack, visibility: none }
+  hands: { shape: list, perPlayer: true, visibility: owner }
+verbs:
+  play:
+    params:
+      cardId: { type: Id, zone: hands }
+      times: { type: u8, min: 1, max: 3 }
+      note: string?
+phases:
+  - id: turn
+    activePlayer: sequential
+    verbs: [play]
+setup:
+  - initZone: { zone: deck, entity: card, count: 2 }
+  - move: { from: deck, to: hands, count: 1, playerSlot: p1 }
+";
+
+    /// The card in p1's hand and the one left in the deck.
+    fn cards(m: &Match) -> (String, String) {
+        let first = |pointer: &str| m.state.pointer(pointer).unwrap()[0].as_str().unwrap().to_string();
+        (first("/zones/hands/p1"), first("/zones/deck"))
+    }
+
+    /// The rejection for `play` with `args` from p1; the state must not have changed.
+    fn rejected(m: &mut Match, args: Value) -> Rejection {
+        let before = m.state.clone();
+        let rejection = m.apply_verb("p1", &json!({ "verb": "play", "args": args })).unwrap_err();
+        assert_eq!(rejection.code, RejectCode::InvalidArgs);
+        assert_eq!(m.state, before);
+        rejection
+    }
+
+    #[test]
+    fn declared_args_of_the_right_type_are_accepted() {
+        let mut m = scratch_match(RULES, 2, 1);
+        let (held, _) = cards(&m);
+        m.apply_verb("p1", &json!({ "verb": "play", "args": { "cardId": held, "times": 3 } })).unwrap();
+    }
+
+    #[test]
+    fn an_unknown_argument_is_rejected() {
+        let mut m = scratch_match(RULES, 2, 1);
+        let (held, _) = cards(&m);
+        let rejection = rejected(&mut m, json!({ "cardId": held, "times": 1, "target": "p2" }));
+        assert_eq!(rejection.reason, "unexpected argument `target` (expected: cardId, times, note)");
+    }
+
+    #[test]
+    fn a_missing_required_argument_is_rejected() {
+        let mut m = scratch_match(RULES, 2, 1);
+        let (held, _) = cards(&m);
+        assert_eq!(rejected(&mut m, json!({ "cardId": held })).reason, "missing argument `times`");
+    }
+
+    #[test]
+    fn an_int_outside_its_range_is_rejected() {
+        let mut m = scratch_match(RULES, 2, 1);
+        let (held, _) = cards(&m);
+        assert_eq!(rejected(&mut m, json!({ "cardId": held, "times": 4 })).reason, "`times`: 4 is outside 1..=3");
+    }
+
+    #[test]
+    fn an_id_outside_its_zone_is_rejected() {
+        let mut m = scratch_match(RULES, 2, 1);
+        let (_, in_deck) = cards(&m);
+        let reason = rejected(&mut m, json!({ "cardId": in_deck, "times": 1 })).reason;
+        assert_eq!(reason, format!("`cardId`: `{}` is not in `hands`", in_deck));
+    }
+}
@@ -0,0 +1,170 @@
+//! patch.rs – RFC 6902 `add` / `remove` / `replace` / `move` on the state document
+//! The engine mutates state only through the first three, recording each op it
+//! applies; `apply` also takes `move` for diffs that come from elsewhere.
+
+use anyhow::{anyhow, bail};
+use serde_json::Value;
+
+/// Apply one JSON-Patch op to `doc`.
+pub fn apply(doc: &mut Value, op: &Value) -> anyhow::Result<()> {
+    let path = op["path"].as_str().ok_or_else(|| anyhow!("patch op without a path: {}", op))?;
+    match op["op"].as_str() {
+        Some("add") => add(doc, path, op["value"].clone()),
+        Some("remove") => remove(doc, path).map(drop),
+        Some("replace") => replace(doc, path, op["value"].clone()),
+        Some("move") => {
+            let from = op["from"].as_str().ok_or_else(|| anyhow!("move op without a from: {}", op))?;
+            let value = remove(doc, from)?;
+            add(doc, path, value.clone()).inspect_err(|_| {
+                // put it back where it was so a failed op leaves `doc` as it found it
+                let _ = add(doc, from, value);
+            })
+        }
+        other => bail!("unsupported patch op {:?}", other.unwrap_or("")),
+    }
+}
+
+/// Insert into an array (index or `-` for the end) or set an object member.
+pub fn add(doc: &mut Value, path: &str, value: Value) -> anyhow::Result<()> {
+    if path.is_empty() {
+        *doc = value;
+        return Ok(());
+    }
+    let (parent, key) = split(path)?;
+    match doc.pointer_mut(parent) {
+        Some(Value::Object(map)) => {
+            map.insert(key, value);
+        }
+        Some(Value::Array(items)) => match key.as_str() {
+            "-" => items.push(value),
+            index => {
+                let i = array_index(index, items.len() + 1, path)?;
+                items.insert(i, value);
+            }
+        },
+        Some(_) => bail!("cannot add below a scalar at {}", path),
+        None => bail!("no parent for {}", path),
+    }
+    Ok(())
+}
+
+/// Remove and return the value at `path`.
+pub fn remove(doc: &mut Value, path: &str) -> anyhow::Result<Value> {
+    let (parent, key) = split(path)?;
+    match doc.pointer_mut(parent) {
+        Some(Value::Object(map)) => map.remove(&key).ok_or_else(|| anyhow!("nothing to remove at {}", path)),
+        Some(Value::Array(items)) => {
+            let i = array_index(&key, items.len(), path)?;
+            Ok(items.remove(i))
+        }
+        _ => bail!("nothing to remove at {}", path),
+    }
+}
+
+/// Overwrite the existing value at `path`.
+pub fn replace(doc: &mut Value, path: &str, value: Value) -> anyhow::Result<()> {
+    let target = doc.pointer_mut(path).ok_or_else(|| anyhow!("nothing to replace at {}", path))?;
+    *target = value;
+    Ok(())
+}
+
+/// JSON Pointer escaping (RFC 6901).
+pub fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+pub fn unescape(key: &str) -> String {
+    key.replace("~1", "/").replace("~0", "~")
+}
+
+fn split(path: &str) -> anyhow::Result<(&str, String)> {
+    let (parent, key) = path.rsplit_once('/').ok_or_else(|| anyhow!("invalid JSON pointer {:?}", path))?;
+    Ok((parent, unescape(key)))
+}
+
+/// Parse an array index that must be below `bound`.
+fn array_index(key: &str, bound: usize, path: &str) -> anyhow::Result<usize> {
+    match key.parse::<usize>() {
+        Ok(i) if i < bound => Ok(i),
+        _ => bail!("index out of range at {}", path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn doc() -> Value {
+        json!({ "zones": { "deck": ["a", "b", "c"], "discard": [] } })
+    }
+
+    fn op(op: Value) -> anyhow::Result<Value> {
+        let mut doc = doc();
+        apply(&mut doc, &op)?;
+        Ok(doc["zones"].clone())
+    }
+
+    #[test]
+    fn add_inserts_at_an_index_or_appends_with_dash() {
+        let zones = op(json!({ "op": "add", "path": "/zones/deck/0", "value": "z" })).unwrap();
+        assert_eq!(zones["deck"], json!(["z", "a", "b", "c"]));
+        let zones = op(json!({ "op": "add", "path": "/zones/deck/3", "value": "z" })).unwrap();
+        assert_eq!(zones["deck"], json!(["a", "b", "c", "z"]), "one past the end appends");
+        let zones = op(json!({ "op": "add", "path": "/zones/deck/-", "value": "z" })).unwrap();
+        assert_eq!(zones["deck"], json!(["a", "b", "c", "z"]));
+        let zones = op(json!({ "op": "add", "path": "/zones/discard/-", "value": "z" })).unwrap();
+        assert_eq!(zones["discard"], json!(["z"]));
+    }
+
+    #[test]
+    fn add_refuses_an_index_past_the_end() {
+        let err = op(json!({ "op": "add", "path": "/zones/deck/4", "value": "z" })).unwrap_err();
+        assert_eq!(err.to_string(), "index out of range at /zones/deck/4");
+        assert!(op(json!({ "op": "add", "path": "/zones/deck/x", "value": "z" })).is_err());
+    }
+
+    #[test]
+    fn remove_takes_an_element_out_and_refuses_missing_ones() {
+        let zones = op(json!({ "op": "remove", "path": "/zones/deck/1" })).unwrap();
+        assert_eq!(zones["deck"], json!(["a", "c"]));
+        let err = op(json!({ "op": "remove", "path": "/zones/deck/3" })).unwrap_err();
+        assert_eq!(err.to_string(), "index out of range at /zones/deck/3");
+        assert!(op(json!({ "op": "remove", "path": "/zones/deck/-" })).is_err(), "`-` names no element");
+        assert!(op(json!({ "op": "remove", "path": "/zones/hand" })).is_err());
+    }
+
+    #[test]
+    fn replace_overwrites_an_existing_element_only() {
+        let zones = op(json!({ "op": "replace", "path": "/zones/deck/2", "value": "z" })).unwrap();
+        assert_eq!(zones["deck"], json!(["a", "b", "z"]));
+        let err = op(json!({ "op": "replace", "path": "/zones/deck/3", "value": "z" })).unwrap_err();
+        assert_eq!(err.to_string(), "nothing to replace at /zones/deck/3");
+        assert!(op(json!({ "op": "replace", "path": "/zones/deck/-", "value": "z" })).is_err());
+    }
+
+    #[test]
+    fn move_relocates_an_element_between_arrays() {
+        let zones = op(json!({ "op": "move", "from": "/zones/deck/0", "path": "/zones/discard/-" })).unwrap();
+        assert_eq!(zones, json!({ "deck": ["b", "c"], "discard": ["a"] }));
+        let zones = op(json!({ "op": "move", "from": "/zones/deck/2", "path": "/zones/deck/0" })).unwrap();
+        assert_eq!(zones["deck"], json!(["c", "a", "b"]));
+    }
+
+    #[test]
+    fn a_failed_move_leaves_the_document_alone() {
+        let mut doc = doc();
+        let err = apply(&mut doc, &json!({ "op": "move", "from": "/zones/deck/1", "path": "/zones/discard/5" })).unwrap_err();
+        assert_eq!(err.to_string(), "index out of range at /zones/discard/5");
+        assert_eq!(doc, self::doc());
+        assert!(apply(&mut doc, &json!({ "op": "move", "from": "/zones/deck/3", "path": "/zones/discard/-" })).is_err());
+        assert!(apply(&mut doc, &json!({ "op": "move", "path": "/zones/discard/-" })).is_err(), "no `from`");
+        assert_eq!(doc, self::doc());
+    }
+
+    #[test]
+    fn unsupported_ops_are_refused() {
+        let err = op(json!({ "op": "copy", "from": "/zones/deck/0", "path": "/zones/discard/-" })).unwrap_err();
+        assert_eq!(err.to_string(), "unsupported patch op \"copy\"");
+    }
+}
@@ -0,0 +1,225 @@
+//! pending.rs – questions a verb waits on
+//! `ask` (the effect, or the host import of the same name) puts a question to
+//! one player in `state.pending` and stops the verb's resolution there. Nobody
+//! moves until that player sends the built-in `answer` verb: a tick of its own
+//! in which the ask's `then` hook gets the choice and the verb carries on.
+//!
+//! ```text
+//! "pending": { "player": "p1", "choice": "PlayerId", "prompt": "Whose hand?",
+//!              "resume": { "then": "guard_target", "as": "target", "context": {...}, "verb": {...} } }
+//! ```
+//! `resume` stays on the server (see visibility.rs).
+
+use super::{resolve::Suspended, Ctx, Match, RejectCode, Rejection, Tick};
+use crate::entities::{HookEvent, ParamSpec, VerbTemplate};
+use anyhow::{anyhow, bail, ensure};
+use indexmap::IndexMap;
+use serde_json::{json, Map, Value};
+
+/// The built-in verb that answers `state.pending`: `{ "verb": "answer", "args": { "choice": ... } }`.
+pub const ANSWER: &str = "answer";
+
+/// What an `ask` puts to a player.
+pub(super) struct Question {
+    pub player: String,
+    /// what a valid answer is, declared like a verb param
+    pub choice: ParamSpec,
+    pub prompt: Option<String>,
+    /// `on_answer` hook that gets the choice
+    pub then: Option<String>,
+    /// verb param name the choice is bound to for the verb's later effects
+    pub bind: Option<String>,
+    /// handed back to `then` untouched
+    pub context: Value,
+}
+
+impl Match {
+    /// The seat a question is waiting on.
+    pub fn pending_player(&self) -> Option<&str> {
+        self.state["pending"]["player"].as_str()
+    }
+
+    /// `answer` for the pending question, as if declared with `params: { choice: ... }`.
+    pub(super) fn answer_verb(&self) -> Option<VerbTemplate> {
+        let choice = serde_json::from_value(self.state["pending"]["choice"].clone()).ok()?;
+        Some(answer_verb(choice))
+    }
+
+    /// Put `question` in `state.pending`. Refused when another question is open,
+    /// the round is over, or the player would have nothing to choose from.
+    pub(super) fn ask(&mut self, question: Question) -> anyhow::Result<()> {
+        if let Some(player) = self.pending_player() {
+            bail!("{} has not answered the last question yet", player);
+        }
+        ensure!(!self.round_decided(), "the round is over");
+        ensure!(self.seat_index(&question.player).is_some(), "unknown player `{}`", question.player);
+        if let Some(then) = &question.then {
+            ensure!(self.bundle.rules.hooks.get(then).is_some_and(|h| h.on == HookEvent::Answer), "`{}` is not an `on_answer` hook", then);
+        }
+        if self.legal_move(ANSWER, &answer_verb(question.choice.clone()), &question.player).is_none() {
+            bail!("{} has nothing to choose from", question.player);
+        }
+        let mut resume = Map::new();
+        resume.extend(question.then.map(|then| ("then".to_string(), json!(then))));
+        resume.extend(question.bind.map(|name| ("as".to_string(), json!(name))));
+        if !question.context.is_null() {
+            resume.insert("context".to_string(), question.context);
+        }
+        let mut pending = json!({ "player": question.player, "choice": question.choice, "resume": resume });
+        if let Some(prompt) = question.prompt {
+            pending["prompt"] = json!(prompt);
+        }
+        self.add("/pending".to_string(), pending)
+    }
+
+    /// `ask(request)` from a hook: `{ player, choice, prompt?, then?, context? }`.
+    pub(super) fn ask_from_hook(&mut self, request: &Value) -> anyhow::Result<()> {
+        let text = |key: &str| match &request[key] {
+            Value::Null => Ok(None),
+            Value::String(s) => Ok(Some(s.clone())),
+            other => Err(anyhow!("ask: `{}` must be a string, got {}", key, other)),
+        };
+        let player = text("player")?.ok_or_else(|| anyhow!("ask: missing `player`"))?;
+        let choice = serde_json::from_value(request["choice"].clone()).map_err(|e| anyhow!("ask: `choice`: {}", e))?;
+        let (prompt, then) = (text("prompt")?, text("then")?);
+        self.ask(Question { player, choice, prompt, then, bind: None, context: request["context"].clone() })
+            .map_err(|e| anyhow!("ask: {:#}", e))
+    }
+
+    /// `answer` from `actor`, as its own tick: the question is cleared, its `then`
+    /// hook runs with the choice, and the verb that asked carries on where it
+    /// stopped, unless `then` asks something else first.
+    pub(super) fn answer(&mut self, actor: &str, args: &Value) -> Result<Tick, Rejection> {
+        let (Some(player), Some(verb)) = (self.pending_player(), self.answer_verb()) else {
+            return Err(Rejection::new(RejectCode::NothingToAnswer, "nothing has been asked"));
+        };
+        if player != actor {
+            return Err(Rejection::new(RejectCode::NotYourTurn, format!("{} is being asked, not {}", player, actor)));
+        }
+        self.check_params(&verb, args, actor)?;
+        let choice = args["choice"].clone();
+        let resume = self.state["pending"]["resume"].clone();
+        let suspended: Option<Suspended> = serde_json::from_value(resume["verb"].clone()).ok().flatten();
+
+        self.invocation = json!({ "actor": actor, "verb": ANSWER, "args": args });
+        let mut round_end = None;
+        let result = self.transaction(|m| {
+            m.next_tick()?;
+            m.remove("/pending".to_string())?;
+            let mut suspended = suspended;
+            if let (Some(s), Some(name)) = (suspended.as_mut(), resume["as"].as_str()) {
+                s.args.insert(name.to_string(), choice.clone());
+            }
+            if let Some(then) = resume["then"].as_str() {
+                let payload = json!({
+                    "event": HookEvent::Answer,
+                    "phase": m.phase_id(),
+                    "actor": actor,
+                    "verb": ANSWER,
+                    "args": args,
+                    "entity": suspended.as_ref().and_then(|s| s.entity.as_deref()),
+                    "answer": choice,
+                    "context": resume["context"],
+                });
+                if m.call_hook(then, &payload)? {
+                    // a follow-up question: the verb waits for that answer as well
+                    if let Some(s) = suspended.take() {
+                        m.add("/pending/resume/verb".to_string(), serde_json::to_value(s)?)?;
+                    }
+                }
+            }
+            if let Some(s) = suspended {
+                let bundle = m.bundle.clone();
+                let verb = bundle.rules.verbs.get(&s.verb).ok_or_else(|| anyhow!("unknown verb `{}`", s.verb))?;
+                m.turn_passed |= s.turn_passed;
+                m.entered_turn_phase |= s.entered_turn_phase;
+                // hooks further down the verb see the verb, not the answer
+                let answering = std::mem::replace(&mut m.invocation, json!({ "actor": s.actor, "verb": s.verb, "args": s.args }));
+                let ctx = Ctx { actor: Some(&s.actor), params: &s.args };
+                let resumed = m.resolve_verb(verb, &s.phase, &ctx, s.entity.as_deref(), s.step, s.hook);
+                m.invocation = answering;
+                resumed?;
+            }
+            round_end = m.close_round()?;
+            Ok(())
+        });
+        self.invocation = Value::Null;
+        self.settle(result, round_end)
+    }
+}
+
+fn answer_verb(choice: ParamSpec) -> VerbTemplate {
+    VerbTemplate { params: IndexMap::from([("choice".to_string(), choice)]), ..VerbTemplate::default() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::scratch_match;
+
+    /// `accuse` asks the accuser whom they mean, then flags that player.
+    const RULES: &str = "
+zones:
+  accused: { shape: flag, perPlayer: true, visibility: all }
+verbs:
+  accuse:
+    effect:
+      - ask: { player: actor, choice: PlayerId, prompt: Whom?, as: target }
+      - setFlag: { zone: accused, player: $target, value: true }
+  pass: {}
+phases:
+  - id: turn
+    activePlayer: sequential
+    verbs: [accuse, pass]
+";
+
+    fn verb(m: &mut Match, seat: &str, verb: &str, args: Value) -> Result<Tick, Rejection> {
+        m.apply_verb(seat, &json!({ "verb": verb, "args": args }))
+    }
+
+    /// Three seats, p1 having just accused.
+    fn asked() -> Match {
+        let mut m = scratch_match(RULES, 3, 1);
+        verb(&mut m, "p1", "accuse", json!({})).unwrap();
+        m
+    }
+
+    #[test]
+    fn an_answer_resumes_the_verb_that_asked() {
+        let mut m = asked();
+        assert_eq!(m.pending_player(), Some("p1"));
+        assert_eq!(m.state["pending"]["prompt"], "Whom?");
+        assert_eq!(m.state["zones"]["accused"], json!({ "p1": false, "p2": false, "p3": false }));
+        // nothing else moves in the meantime
+        assert_eq!(verb(&mut m, "p1", "pass", json!({})).unwrap_err().code, RejectCode::DecisionPending);
+        let moves = m.legal_moves("p1");
+        assert_eq!(moves.iter().map(|mv| mv.verb.as_str()).collect::<Vec<_>>(), [ANSWER]);
+
+        verb(&mut m, "p1", ANSWER, json!({ "choice": "p3" })).unwrap();
+        assert_eq!(m.pending_player(), None);
+        assert!(m.state.get("pending").is_none());
+        assert_eq!(m.state["zones"]["accused"], json!({ "p1": false, "p2": false, "p3": true }));
+        assert_eq!(verb(&mut m, "p1", ANSWER, json!({ "choice": "p3" })).unwrap_err().code, RejectCode::NothingToAnswer);
+    }
+
+    #[test]
+    fn only_the_player_asked_can_answer() {
+        let mut m = asked();
+        let before = m.state.clone();
+        let rejection = verb(&mut m, "p2", ANSWER, json!({ "choice": "p3" })).unwrap_err();
+        assert_eq!(rejection.code, RejectCode::NotYourTurn);
+        assert_eq!(rejection.reason, "p1 is being asked, not p2");
+        assert_eq!(m.state, before);
+    }
+
+    #[test]
+    fn an_answer_that_was_not_offered_is_rejected() {
+        let mut m = asked();
+        let before = m.state.clone();
+        let rejection = verb(&mut m, "p1", ANSWER, json!({ "choice": "p4" })).unwrap_err();
+        assert_eq!(rejection.code, RejectCode::InvalidArgs);
+        assert_eq!(rejection.reason, "`choice`: no player `p4`");
+        assert_eq!(m.state, before);
+        assert_eq!(m.pending_player(), Some("p1"), "the question is still open");
+    }
+}
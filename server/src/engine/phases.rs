@@ -0,0 +1,151 @@
+//! phases.rs – phase / turn-order state machine
+//! Its whole state is `turn: { player, phase, tick }` in the match state, so it
+//! travels to clients through the same diffs as everything else.
+
+use super::{Match, ELIMINATED};
+use crate::entities::{ActivePlayer, ZoneShape};
+use serde_json::{json, Value};
+
+impl Match {
+    /// Initial `turn` object: first seat, first phase, tick 1.
+    pub(super) fn initial_turn(seats: &[String], first_phase: Option<&str>) -> Value {
+        json!({ "player": seats.first(), "phase": first_phase, "tick": 1 })
+    }
+
+    pub fn turn_player(&self) -> Option<&str> {
+        self.state["turn"]["player"].as_str()
+    }
+
+    pub fn phase_id(&self) -> &str {
+        self.state["turn"]["phase"].as_str().unwrap_or_default()
+    }
+
+    pub fn tick(&self) -> u64 {
+        self.state["turn"]["tick"].as_u64().unwrap_or(0)
+    }
+
+    /// Seats that may act now: the turn player in a sequential phase, every
+    /// remaining player in a phase without `activePlayer`.
+    pub fn active_players(&self) -> Vec<String> {
+        let phase = self.bundle.rules.phase(self.phase_id());
+        match phase.and_then(|p| p.active_player) {
+            Some(ActivePlayer::Sequential) => self.turn_player().map(|p| vec![p.to_string()]).unwrap_or_default(),
+            // rejected at load time (spec 0.1); treated like an open phase
+            Some(ActivePlayer::Simultaneous) | None => {
+                self.seats().into_iter().filter(|s| !self.is_eliminated(s)).collect()
+            }
+        }
+    }
+
+    /// A seat is out when the bundle has a per-player `eliminated` flag set for it.
+    pub fn is_eliminated(&self, seat: &str) -> bool {
+        let tracked = self
+            .bundle
+            .rules
+            .zones
+            .get(ELIMINATED)
+            .is_some_and(|z| z.per_player && z.shape == ZoneShape::Flag);
+        tracked && self.state["zones"][ELIMINATED][seat] == true
+    }
+
+    /// Count the tick an accepted verb happens in.
+    pub(super) fn next_tick(&mut self) -> anyhow::Result<()> {
+        self.replace("/turn/tick".to_string(), json!(self.tick() + 1))
+    }
+
+    /// Pass the turn to the next seat still in the round and go back to the turn phase.
+    pub(super) fn advance_turn(&mut self) -> anyhow::Result<()> {
+        self.turn_passed = true;
+        let seats = self.seats();
+        let current = seats.iter().position(|s| Some(s.as_str()) == self.turn_player()).unwrap_or(0);
+        let next = (1..=seats.len())
+            .map(|step| &seats[(current + step) % seats.len()])
+            .find(|s| !self.is_eliminated(s))
+            .cloned();
+        if let Some(next) = next {
+            if self.turn_player() != Some(next.as_str()) {
+                self.replace("/turn/player".to_string(), json!(next))?;
+            }
+        }
+        if let Some(turn_phase) = self.turn_phase() {
+            if self.phase_id() != turn_phase {
+                self.replace("/turn/phase".to_string(), json!(turn_phase))?;
+                self.entered_turn_phase = true;
+            }
+        }
+        Ok(())
+    }
+
+    /// The phase a turn starts in: the first one that offers verbs.
+    fn turn_phase(&self) -> Option<String> {
+        self.bundle.rules.phases.iter().find(|p| !p.verbs.is_empty()).map(|p| p.id.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine::{scratch_bundle, Match};
+    use crate::hooks::test_script::{call, module, ADVANCE_TURN, COUNT};
+    use serde_json::json;
+
+    const RULES: &str = "
+verbs:
+  go: { nextPhase: resolve }
+  stay: { nextPhase: turn }
+phases:
+  - id: turn
+    activePlayer: sequential
+    verbs: [go, stay]
+  - id: resolve
+    verbs: []
+hooks:
+  turn_start: { on: on_phase_start, phase: turn }
+";
+
+    /// Two seats and a script whose `turn_start` counts the starts of the turn
+    /// phase; its `resolve_hook` subscribes to `subscription` and runs `body`.
+    fn table(subscription: &str, body: Vec<u8>) -> Match {
+        let rules = format!("{}  resolve_hook: {}\n", RULES, subscription);
+        let script = module(&[("turn_start", COUNT.to_vec()), ("resolve_hook", body)], &[]);
+        let names = ["ann".to_string(), "bob".to_string()];
+        Match::new(scratch_bundle(&rules, Some(&script)), &names, 1).unwrap()
+    }
+
+    fn turn_starts(m: &mut Match) -> i32 {
+        m.hooks.as_mut().unwrap().global_i32("count").unwrap()
+    }
+
+    #[test]
+    fn passing_the_turn_back_into_the_turn_phase_starts_it() {
+        let mut m = table("on_phase_end", Vec::new());
+        m.apply_verb("p1", &json!({ "verb": "go", "args": {} })).unwrap();
+        assert_eq!((m.turn_player(), m.phase_id()), (Some("p2"), "turn"));
+        assert_eq!(turn_starts(&mut m), 1);
+        m.apply_verb("p2", &json!({ "verb": "go", "args": {} })).unwrap();
+        assert_eq!(turn_starts(&mut m), 2);
+    }
+
+    #[test]
+    fn a_hook_passing_the_turn_starts_the_turn_phase_once() {
+        let mut m = table("{ on: on_phase_start, phase: resolve }", call(ADVANCE_TURN, None));
+        m.apply_verb("p1", &json!({ "verb": "go", "args": {} })).unwrap();
+        assert_eq!((m.turn_player(), m.phase_id()), (Some("p2"), "turn"));
+        assert_eq!(turn_starts(&mut m), 1);
+    }
+
+    #[test]
+    fn next_phase_into_the_turn_phase_starts_it_once() {
+        let mut m = table("on_phase_end", Vec::new());
+        m.apply_verb("p1", &json!({ "verb": "stay", "args": {} })).unwrap();
+        assert_eq!((m.turn_player(), m.phase_id()), (Some("p1"), "turn"));
+        assert_eq!(turn_starts(&mut m), 1);
+    }
+
+    #[test]
+    fn a_phase_hook_without_a_phase_runs_at_every_phase_start() {
+        // `resolve_hook` counts the start of `resolve` and of `turn`, `turn_start` only the latter
+        let mut m = table("on_phase_start", COUNT.to_vec());
+        m.apply_verb("p1", &json!({ "verb": "go", "args": {} })).unwrap();
+        assert_eq!(turn_starts(&mut m), 3);
+    }
+}
@@ -0,0 +1,260 @@
+//! predicates.rs – built-in `pre:` conditions
+//! Each entry declares its arguments (checked when the bundle loads) and an
+//! evaluator that explains why it does not hold.
+
+use super::{opt, req, Args, ArgType, Match, Param, ELIMINATED};
+use anyhow::{bail, ensure};
+use serde_json::Value;
+
+pub struct Predicate {
+    pub name: &'static str,
+    pub params: &'static [Param],
+    /// `Ok(())` when the condition holds, otherwise the reason it doesn't.
+    pub eval: fn(&Match, &Args) -> anyhow::Result<()>,
+}
+
+/// The registry. New predicates only need an entry here.
+pub static PREDICATES: &[Predicate] = &[
+    Predicate { name: "turnOf", params: &[req("player", ArgType::Player)], eval: turn_of },
+    Predicate {
+        name: "coordInBounds",
+        params: &[req("r", ArgType::Int), req("c", ArgType::Int), req("w", ArgType::Int), req("h", ArgType::Int)],
+        eval: coord_in_bounds,
+    },
+    Predicate {
+        name: "emptyCell",
+        params: &[req("zone", ArgType::Zone), req("row", ArgType::Int), req("col", ArgType::Int)],
+        eval: empty_cell,
+    },
+    Predicate { name: "zoneNotEmpty", params: &[req("zone", ArgType::Zone), opt("player", ArgType::Player)], eval: zone_not_empty },
+    Predicate {
+        name: "holds",
+        params: &[req("player", ArgType::Player), req("zone", ArgType::Zone), req("entity", ArgType::Entity)],
+        eval: holds,
+    },
+    Predicate { name: "notImmune", params: &[req("player", ArgType::Player), opt("zone", ArgType::Zone)], eval: not_immune },
+    Predicate { name: "stillInRound", params: &[req("player", ArgType::Player), opt("zone", ArgType::Zone)], eval: still_in_round },
+    Predicate { name: "differentPlayer", params: &[req("target", ArgType::Player)], eval: different_player },
+    Predicate { name: "targetNotSelf", params: &[req("target", ArgType::Player)], eval: different_player },
+    Predicate { name: "playersAlive", params: &[req("countMin", ArgType::Int), opt("zone", ArgType::Zone)], eval: players_alive },
+];
+
+pub fn lookup(name: &str) -> Option<&'static Predicate> {
+    PREDICATES.iter().find(|p| p.name == name)
+}
+
+/* --------------------------------------------------------------------------
+   evaluators
+   ----------------------------------------------------------------------- */
+
+fn turn_of(m: &Match, a: &Args) -> anyhow::Result<()> {
+    let player = a.player("player")?;
+    let turn = m.turn_player().unwrap_or("nobody");
+    ensure!(turn == player, "it is {}'s turn, not {}'s", turn, player);
+    Ok(())
+}
+
+fn coord_in_bounds(_: &Match, a: &Args) -> anyhow::Result<()> {
+    let (r, c, w, h) = (a.int("r")?, a.int("c")?, a.int("w")?, a.int("h")?);
+    ensure!((0..h).contains(&r) && (0..w).contains(&c), "{},{} is outside the {}x{} board", r, c, w, h);
+    Ok(())
+}
+
+fn empty_cell(m: &Match, a: &Args) -> anyhow::Result<()> {
+    let zone = a.str("zone")?;
+    let (r, c) = (a.int("row")?, a.int("col")?);
+    let cell = m.zone(zone, None)?.get(r as usize).and_then(|row| row.get(c as usize));
+    match cell {
+        Some(Value::Null) => Ok(()),
+        Some(_) => bail!("cell {},{} of `{}` is occupied", r, c, zone),
+        None => bail!("cell {},{} is not on `{}`", r, c, zone),
+    }
+}
+
+fn zone_not_empty(m: &Match, a: &Args) -> anyhow::Result<()> {
+    let zone = a.str("zone")?;
+    let player = a.opt_player("player")?;
+    let empty = m.zone(zone, player)?.as_array().is_none_or(Vec::is_empty);
+    ensure!(!empty, "`{}` is empty", zone);
+    Ok(())
+}
+
+fn holds(m: &Match, a: &Args) -> anyhow::Result<()> {
+    let (player, zone, entity) = (a.player("player")?, a.str("zone")?, a.str("entity")?);
+    ensure!(m.find_in(m.zone(zone, Some(player))?, entity).is_some(), "{} has no `{}` in `{}`", player, entity, zone);
+    Ok(())
+}
+
+fn not_immune(m: &Match, a: &Args) -> anyhow::Result<()> {
+    let player = a.player("player")?;
+    ensure!(!m.flag(a.opt_str("zone")?.unwrap_or("immunes"), player)?, "{} is immune", player);
+    Ok(())
+}
+
+fn still_in_round(m: &Match, a: &Args) -> anyhow::Result<()> {
+    let player = a.player("player")?;
+    ensure!(!m.flag(a.opt_str("zone")?.unwrap_or(ELIMINATED), player)?, "{} is out of the round", player);
+    Ok(())
+}
+
+fn different_player(_: &Match, a: &Args) -> anyhow::Result<()> {
+    let target = a.player("target")?;
+    ensure!(Some(target) != a.actor.as_deref(), "{} cannot target themselves", target);
+    Ok(())
+}
+
+fn players_alive(m: &Match, a: &Args) -> anyhow::Result<()> {
+    let min = a.int("countMin")?;
+    let zone = a.opt_str("zone")?.unwrap_or(ELIMINATED);
+    let alive = m.seats().iter().filter(|s| !m.flag(zone, s).unwrap_or(false)).count() as i64;
+    ensure!(alive >= min, "only {} players are left, {} needed", alive, min);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::scratch_match;
+    use serde_json::json;
+
+    const RULES: &str = "
+entities:
+  guard: { kind: card }
+  priest: { kind: card }
+zones:
+  deck: { shape: stack, visibility: none }
+  hands: { shape: list, perPlayer: true, visibility: owner }
+  board: { shape: grid, width: 2, height: 2, visibility: all }
+  immunes: { shape: flag, perPlayer: true, visibility: all }
+  eliminated: { shape: flag, perPlayer: true, visibility: all }
+phases:
+  - id: turn
+    activePlayer: sequential
+setup:
+  - initZone: { zone: deck, entity: guard, count: 2 }
+  - initZone: { zone: deck, entity: priest }
+  - move: { from: deck, to: hands, playerSlot: p1, entity: priest }
+  - move: { from: deck, to: board, row: 0, col: 1 }
+  - setFlag: { zone: immunes, player: p2, value: true }
+";
+
+    /// Three seats; p1 is on turn and holds the priest, p2 is immune, one
+    /// guard is left in the deck and the other sits at 0,1 on the board.
+    fn table() -> Match {
+        scratch_match(RULES, 3, 1)
+    }
+
+    /// Evaluate predicate `name` with `args` as `actor` would.
+    fn check(m: &Match, actor: &str, name: &str, args: serde_json::Value) -> Result<(), String> {
+        let args = Args { actor: Some(actor.to_string()), values: args.as_object().unwrap().clone() };
+        (lookup(name).unwrap().eval)(m, &args).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn turn_of_holds_only_for_the_player_on_turn() {
+        let m = table();
+        assert_eq!(check(&m, "p1", "turnOf", json!({ "player": "p1" })), Ok(()));
+        assert_eq!(check(&m, "p2", "turnOf", json!({ "player": "p2" })), Err("it is p1's turn, not p2's".into()));
+    }
+
+    #[test]
+    fn coord_in_bounds_rejects_cells_off_the_board() {
+        let m = table();
+        assert_eq!(check(&m, "p1", "coordInBounds", json!({ "r": 1, "c": 2, "w": 3, "h": 2 })), Ok(()));
+        assert_eq!(
+            check(&m, "p1", "coordInBounds", json!({ "r": 2, "c": 0, "w": 3, "h": 2 })),
+            Err("2,0 is outside the 3x2 board".into())
+        );
+        assert!(check(&m, "p1", "coordInBounds", json!({ "r": -1, "c": 0, "w": 3, "h": 2 })).is_err());
+    }
+
+    #[test]
+    fn empty_cell_tells_occupied_from_off_grid() {
+        let m = table();
+        assert_eq!(check(&m, "p1", "emptyCell", json!({ "zone": "board", "row": 0, "col": 0 })), Ok(()));
+        assert_eq!(
+            check(&m, "p1", "emptyCell", json!({ "zone": "board", "row": 0, "col": 1 })),
+            Err("cell 0,1 of `board` is occupied".into())
+        );
+        assert_eq!(
+            check(&m, "p1", "emptyCell", json!({ "zone": "board", "row": 5, "col": 0 })),
+            Err("cell 5,0 is not on `board`".into())
+        );
+    }
+
+    #[test]
+    fn zone_not_empty_looks_at_the_given_players_slot() {
+        let m = table();
+        assert_eq!(check(&m, "p1", "zoneNotEmpty", json!({ "zone": "deck" })), Ok(()));
+        assert_eq!(check(&m, "p1", "zoneNotEmpty", json!({ "zone": "hands", "player": "p1" })), Ok(()));
+        assert_eq!(
+            check(&m, "p1", "zoneNotEmpty", json!({ "zone": "hands", "player": "p2" })),
+            Err("`hands` is empty".into())
+        );
+    }
+
+    #[test]
+    fn holds_matches_a_template_or_an_instance() {
+        let m = table();
+        let priest = m.state["zones"]["hands"]["p1"][0].as_str().unwrap().to_string();
+        assert_eq!(check(&m, "p1", "holds", json!({ "player": "p1", "zone": "hands", "entity": "priest" })), Ok(()));
+        assert_eq!(check(&m, "p1", "holds", json!({ "player": "p1", "zone": "hands", "entity": priest })), Ok(()));
+        assert_eq!(
+            check(&m, "p1", "holds", json!({ "player": "p1", "zone": "hands", "entity": "guard" })),
+            Err("p1 has no `guard` in `hands`".into())
+        );
+    }
+
+    #[test]
+    fn not_immune_reads_the_immunes_flag() {
+        let m = table();
+        assert_eq!(check(&m, "p1", "notImmune", json!({ "player": "p3" })), Ok(()));
+        assert_eq!(check(&m, "p1", "notImmune", json!({ "player": "p2" })), Err("p2 is immune".into()));
+        assert_eq!(check(&m, "p1", "notImmune", json!({ "player": "p9" })), Err("unknown player `p9`".into()));
+    }
+
+    #[test]
+    fn still_in_round_reads_the_eliminated_flag_or_the_given_zone() {
+        let mut m = table();
+        assert_eq!(check(&m, "p1", "stillInRound", json!({ "player": "p3" })), Ok(()));
+        m.set_flag(ELIMINATED, Some("p3"), true).unwrap();
+        assert_eq!(check(&m, "p1", "stillInRound", json!({ "player": "p3" })), Err("p3 is out of the round".into()));
+        assert_eq!(
+            check(&m, "p1", "stillInRound", json!({ "player": "p2", "zone": "immunes" })),
+            Err("p2 is out of the round".into())
+        );
+    }
+
+    #[test]
+    fn different_player_and_target_not_self_refuse_the_actor() {
+        let m = table();
+        for name in ["differentPlayer", "targetNotSelf"] {
+            assert_eq!(check(&m, "p1", name, json!({ "target": "p2" })), Ok(()));
+            assert_eq!(check(&m, "p1", name, json!({ "target": "p1" })), Err("p1 cannot target themselves".into()));
+        }
+    }
+
+    #[test]
+    fn players_alive_counts_seats_not_eliminated() {
+        let mut m = table();
+        assert_eq!(check(&m, "p1", "playersAlive", json!({ "countMin": 3 })), Ok(()));
+        m.set_flag(ELIMINATED, Some("p2"), true).unwrap();
+        assert_eq!(
+            check(&m, "p1", "playersAlive", json!({ "countMin": 3 })),
+            Err("only 2 players are left, 3 needed".into())
+        );
+        assert_eq!(
+            check(&m, "p1", "playersAlive", json!({ "countMin": 3, "zone": "immunes" })),
+            Err("only 2 players are left, 3 needed".into())
+        );
+    }
+
+    #[test]
+    fn a_wrongly_typed_argument_is_reported() {
+        let m = table();
+        assert_eq!(
+            check(&m, "p1", "coordInBounds", json!({ "r": "one", "c": 0, "w": 3, "h": 3 })),
+            Err("`r` must be an integer, got \"one\"".into())
+        );
+    }
+}
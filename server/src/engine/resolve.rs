@@ -0,0 +1,135 @@
+//! resolve.rs – a verb's effects, hooks and `nextPhase`, as a list of steps
+//! Resolution can stop at any step, when an effect or a hook asks a question
+//! (see pending.rs), and pick up at the same place once it is answered.
+
+use super::{Ctx, Match};
+use crate::entities::{Call, HookEvent, Rules, VerbTemplate};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+/// One step of resolving a verb.
+enum Step<'a> {
+    Effect(&'a Call),
+    /// hooks for an event, in the phase the verb was played in
+    Fire(HookEvent),
+    /// `on_phase_end` of whatever phase `nextPhase` leaves
+    LeavePhase,
+    Enter(&'a str),
+    FireIn(HookEvent, &'a str),
+    /// back to the turn phase with the next player, unless the turn has moved on already
+    PassTurn,
+    /// `on_phase_start` of the turn phase, if passing the turn went back into it
+    StartTurn,
+}
+
+/// A verb waiting on an answer: enough to carry on where it stopped.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct Suspended {
+    pub verb: String,
+    pub actor: String,
+    /// the verb's args, plus the answers bound with `as`
+    pub args: Map<String, Value>,
+    pub entity: Option<String>,
+    /// the phase it was played in
+    pub phase: String,
+    pub step: usize,
+    /// registry index of the next hook, when it stopped inside a step's hooks
+    pub hook: usize,
+    pub turn_passed: bool,
+    pub entered_turn_phase: bool,
+}
+
+/// The steps of `verb`. Phases without verbs (Love Letter's `resolve`, tic-tac-toe's
+/// `checkWin`) only exist for hooks: they start and end in the same step, then play
+/// returns to the turn phase with the next player. Whenever the turn passes back
+/// into the turn phase, by that step or by a hook, the turn phase starts last.
+fn steps<'a>(verb: &'a VerbTemplate, rules: &Rules) -> Vec<Step<'a>> {
+    let mut steps = Vec::new();
+    for effect in &verb.effect {
+        steps.push(Step::Effect(effect));
+        steps.push(Step::Fire(HookEvent::AfterEffect));
+    }
+    steps.push(Step::Fire(HookEvent::AfterPlay));
+    if let Some(next) = verb.next_phase.as_deref() {
+        steps.extend([Step::LeavePhase, Step::Enter(next), Step::FireIn(HookEvent::PhaseStart, next)]);
+        if rules.phase(next).is_some_and(|p| p.verbs.is_empty()) {
+            steps.extend([Step::FireIn(HookEvent::PhaseEnd, next), Step::PassTurn]);
+        }
+    }
+    steps.push(Step::StartTurn);
+    steps
+}
+
+impl Match {
+    /// Run the steps of `verb` from `step` (and, within it, from hook `hook`) on.
+    /// Once a hook ends the round only effects still run. When a step asks a
+    /// question, the rest is written to `pending.resume.verb` and left for the answer.
+    pub(super) fn resolve_verb(
+        &mut self,
+        verb: &VerbTemplate,
+        phase: &str,
+        ctx: &Ctx,
+        entity: Option<&str>,
+        step: usize,
+        hook: usize,
+    ) -> anyhow::Result<()> {
+        let bundle = self.bundle.clone();
+        let steps = steps(verb, &bundle.rules);
+        for (index, current) in steps.iter().enumerate().skip(step) {
+            let from = if index == step { hook } else { 0 };
+            if self.round_decided() && !matches!(current, Step::Effect(_)) {
+                continue;
+            }
+            let stopped = match current {
+                Step::Effect(effect) => {
+                    self.run_effect(effect, ctx)?;
+                    self.state.get("pending").is_some().then_some((index + 1, 0))
+                }
+                Step::Fire(event) => self.fire(*event, phase, entity, from)?.map(|next| (index, next)),
+                Step::LeavePhase => {
+                    let leaving = self.phase_id().to_string();
+                    self.fire(HookEvent::PhaseEnd, &leaving, None, from)?.map(|next| (index, next))
+                }
+                Step::Enter(next) => {
+                    // this entry's own start hooks run next; a turn passed earlier is left behind
+                    self.entered_turn_phase = false;
+                    self.replace("/turn/phase".to_string(), json!(next))?;
+                    None
+                }
+                Step::FireIn(event, in_phase) => self.fire(*event, in_phase, None, from)?.map(|next| (index, next)),
+                Step::PassTurn => {
+                    if !self.turn_passed {
+                        self.advance_turn()?;
+                    }
+                    None
+                }
+                Step::StartTurn if self.entered_turn_phase => {
+                    let turn_phase = self.phase_id().to_string();
+                    let stopped = self.fire(HookEvent::PhaseStart, &turn_phase, None, from)?;
+                    if stopped.is_none() {
+                        self.entered_turn_phase = false;
+                    }
+                    stopped.map(|next| (index, next))
+                }
+                Step::StartTurn => None,
+            };
+            if let Some((step, hook)) = stopped {
+                let suspended = Suspended {
+                    verb: self.invocation["verb"].as_str().unwrap_or_default().to_string(),
+                    actor: ctx.actor.unwrap_or_default().to_string(),
+                    args: ctx.params.clone(),
+                    entity: entity.map(str::to_string),
+                    phase: phase.to_string(),
+                    step,
+                    hook,
+                    turn_passed: self.turn_passed,
+                    entered_turn_phase: self.entered_turn_phase,
+                };
+                self.add("/pending/resume/verb".to_string(), serde_json::to_value(suspended)?)?;
+                return Ok(());
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,166 @@
+//! rng.rs – the match's deterministic random numbers
+//! ChaCha20 keyed from the match seed, so a seed yields the same sequence on
+//! every build, while the numbers players see (shuffles, `random` choices, the
+//! hooks' `random` import) give away neither the seed nor what comes next.
+//! Entity handles come from a second stream, HMAC-SHA256 over a counter under
+//! its own key, so nothing the gameplay stream draws is ever sent out as-is.
+//! Together they make a match reproducible from its seed and its list of actions.
+
+use hmac::{Hmac, Mac};
+use rand_chacha::rand_core::{RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use sha2::{Digest, Sha256};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rng {
+    stream: ChaCha20Rng,
+    handle_key: [u8; 32],
+    /// numbers drawn since `begin_tick`, in order
+    drawn: Vec<u64>,
+    /// handles issued so far
+    pub handles: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            stream: ChaCha20Rng::from_seed(derive_key(b"bluefelt rng", seed)),
+            handle_key: derive_key(b"bluefelt handles", seed),
+            drawn: Vec::new(),
+            handles: 0,
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let n = self.stream.next_u64();
+        self.drawn.push(n);
+        n
+    }
+
+    /// Start recording the numbers a new tick draws.
+    pub fn begin_tick(&mut self) {
+        self.drawn.clear();
+    }
+
+    /// `sha256-<hex>` of the numbers drawn since `begin_tick`; two runs of a
+    /// tick drew the same numbers exactly when their digests match.
+    pub fn tick_digest(&self) -> String {
+        let hasher = self.drawn.iter().fold(Sha256::new(), |h, n| h.chain_update(n.to_le_bytes()));
+        let hex: String = hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect();
+        format!("sha256-{}", hex)
+    }
+
+    /// Uniform in `0..n` (always 0 when `n` is 0), without modulo bias.
+    pub fn below(&mut self, n: u64) -> u64 {
+        if n == 0 {
+            return 0;
+        }
+        let zone = u64::MAX - u64::MAX % n;
+        loop {
+            let x = self.next_u64();
+            if x < zone {
+                return x % n;
+            }
+        }
+    }
+
+    /// 48 bits for the next entity handle, from the handle stream.
+    pub fn next_handle(&mut self) -> u64 {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.handle_key).expect("HMAC takes keys of any length");
+        mac.update(&self.handles.to_le_bytes());
+        self.handles += 1;
+        let digest = mac.finalize().into_bytes();
+        digest[..6].iter().fold(0, |n, b| n << 8 | u64::from(*b))
+    }
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+/// A 256-bit key for one use of `seed`; `label` keeps the two streams apart.
+fn derive_key(label: &[u8], seed: u64) -> [u8; 32] {
+    Sha256::new().chain_update(label).chain_update(seed.to_le_bytes()).finalize().into()
+}
+
+/// A seed for a new match, from the OS-seeded std hasher.
+pub fn fresh_seed() -> u64 {
+    use std::hash::{BuildHasher, Hasher};
+    let mut h = std::collections::hash_map::RandomState::new().build_hasher();
+    h.write_u64(0);
+    h.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{scratch_match, Match};
+    use serde_json::json;
+
+    /// Eight distinct cards, so a deck's order shows in its templates.
+    fn rules() -> String {
+        let cards = ["ace", "two", "three", "four", "five", "six", "seven", "eight"];
+        let mut rules = "entities:\n".to_string();
+        for card in cards {
+            rules.push_str(&format!("  {}: {{ kind: card }}\n", card));
+        }
+        rules.push_str(
+            "zones:
+  deck: { shape: stack, visibility: none }
+  hands: { shape: list, perPlayer: true, visibility: owner }
+verbs:
+  mix: { effect: [{ shuffle: { zone: deck } }] }
+  draw: { effect: [{ move: { from: deck, to: hands, count: 1, playerSlot: actor } }] }
+phases:
+  - id: turn
+    activePlayer: sequential
+    verbs: [mix, draw]
+setup:
+",
+        );
+        for card in cards {
+            rules.push_str(&format!("  - initZone: {{ zone: deck, entity: {} }}\n", card));
+        }
+        rules + "  - shuffle: { zone: deck }\n"
+    }
+
+    /// A match with `seed` after p1 mixed the deck and drew twice.
+    fn played(seed: u64) -> Match {
+        let mut m = scratch_match(&rules(), 2, seed);
+        for verb in ["mix", "draw", "draw"] {
+            m.apply_verb("p1", &json!({ "verb": verb, "args": {} })).unwrap();
+        }
+        m
+    }
+
+    #[test]
+    fn a_seed_always_gives_the_same_numbers_and_handles() {
+        let (mut a, mut b) = (Rng::new(42), Rng::new(42));
+        for _ in 0..8 {
+            assert_eq!(a.next_u64(), b.next_u64());
+            assert_eq!(a.next_handle(), b.next_handle());
+        }
+        assert_eq!(a.below(52), b.below(52));
+    }
+
+    #[test]
+    fn the_same_seed_and_verbs_give_the_same_match() {
+        let (a, b) = (played(42), played(42));
+        // the state holds the shuffled deck and every handle
+        assert_eq!(a.state, b.state);
+        let diffs = |m: &Match| m.log.events.iter().map(|e| e.diff.clone()).collect::<Vec<_>>();
+        assert_eq!(diffs(&a), diffs(&b));
+    }
+
+    #[test]
+    fn different_seeds_shuffle_differently() {
+        let order = |m: &Match| {
+            let deck = m.state["zones"]["deck"].as_array().unwrap();
+            deck.iter().map(|id| m.state["entities"][id.as_str().unwrap()]["template"].clone()).collect::<Vec<_>>()
+        };
+        assert_ne!(order(&played(1)), order(&played(2)));
+        assert_ne!(Rng::new(1).next_handle(), Rng::new(2).next_handle());
+    }
+}
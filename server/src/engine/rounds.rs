@@ -0,0 +1,211 @@
+//! rounds.rs – rounds, scores and the end of the match
+//! A hook's `round_end(winner)` scores a point for the winner and stops the
+//! round's remaining hooks. Once the tick is done, `match:` decides: either the
+//! match has a `result`, or the next round is dealt in the same tick (zones
+//! emptied, entities discarded, supply and `setup` run again).
+
+use super::{empty_zones, escape, Match};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// How a round ended; sent to every player after the tick's event, and logged with it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoundEnd {
+    pub round: u64,
+    /// seat, or `null` for a draw
+    pub winner: Value,
+    /// seat → points after this round
+    pub scores: Value,
+    /// the match's `result` when this round ended it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+}
+
+impl RoundEnd {
+    /// `roundEnd`, then `gameOver` if this round ended the match; the same for
+    /// every recipient, sent after the event of tick `tick`.
+    pub fn envelopes(&self, tick: u64, seq: u64) -> Vec<Value> {
+        let mut envelopes = vec![json!({
+            "type": "roundEnd",
+            "tick": tick,
+            "seq": seq,
+            "round": self.round,
+            "winner": self.winner,
+            "scores": self.scores,
+        })];
+        if let Some(result) = &self.result {
+            envelopes.push(json!({
+                "type": "gameOver",
+                "tick": tick,
+                "seq": seq,
+                "winner": result["winner"],
+                "scores": result["scores"],
+            }));
+        }
+        envelopes
+    }
+}
+
+impl Match {
+    pub fn round(&self) -> u64 {
+        self.state["round"].as_u64().unwrap_or(1)
+    }
+
+    /// `round_end(winner)` from a hook: a point for `winner` (`draw`: nobody).
+    pub(super) fn end_round(&mut self, winner: &str) -> anyhow::Result<()> {
+        let winner = match winner {
+            "draw" => Value::Null,
+            seat if self.seats().iter().any(|s| s == seat) => json!(seat),
+            other => anyhow::bail!("round_end: unknown player `{}`", other),
+        };
+        if let Some(seat) = winner.as_str() {
+            let score = self.state["scores"][seat].as_u64().unwrap_or(0);
+            self.replace(format!("/scores/{}", escape(seat)), json!(score + 1))?;
+        }
+        self.round_over = Some(winner);
+        Ok(())
+    }
+
+    /// Whether nothing more happens in this round: it ended, or the match did.
+    pub(super) fn round_decided(&self) -> bool {
+        self.round_over.is_some() || self.is_over()
+    }
+
+    /// After a tick's verb and hooks: end the match or deal the next round if
+    /// a hook ended this one.
+    pub(super) fn close_round(&mut self) -> anyhow::Result<Option<RoundEnd>> {
+        let Some(winner) = self.round_over.take() else { return Ok(None) };
+        let (round, scores, seats) = (self.round(), self.state["scores"].clone(), self.seats());
+        let rules = &self.bundle.rules.match_rules;
+        let points = |seat: &String| scores[seat.as_str()].as_u64().unwrap_or(0);
+
+        let reached = rules.target_score(seats.len()).and_then(|target| seats.iter().find(|s| points(s) >= target as u64));
+        let result = if let Some(seat) = reached {
+            Some(json!({ "winner": seat, "scores": scores }))
+        } else if rules.rounds.is_some_and(|n| round >= n as u64) {
+            // out of rounds: the highest score wins, a tie is a draw
+            let best = seats.iter().map(points).max().unwrap_or(0);
+            let leaders: Vec<&String> = seats.iter().filter(|s| points(s) == best).collect();
+            let winner = if leaders.len() == 1 { json!(leaders[0]) } else { Value::Null };
+            Some(json!({ "winner": winner, "scores": scores }))
+        } else {
+            None
+        };
+        match &result {
+            Some(result) => self.add("/result".to_string(), result.clone())?,
+            None => self.next_round()?,
+        }
+        Ok(Some(RoundEnd { round, winner, scores, result }))
+    }
+
+    /// Clear the table and deal again: every entity is discarded and recreated
+    /// under a fresh handle, and play restarts in the first phase.
+    fn next_round(&mut self) -> anyhow::Result<()> {
+        self.replace("/round".to_string(), json!(self.round() + 1))?;
+        let ids: Vec<String> = self.state["entities"].as_object().map(|es| es.keys().cloned().collect()).unwrap_or_default();
+        for id in ids {
+            self.remove(format!("/entities/{}", escape(&id)))?;
+        }
+        for (zone, empty) in empty_zones(&self.bundle.rules, &self.seats()) {
+            if self.state["zones"][&zone] != empty {
+                self.replace(format!("/zones/{}", escape(&zone)), empty)?;
+            }
+        }
+        let first = Self::initial_turn(&self.seats(), self.bundle.rules.phases.first().map(|p| p.id.as_str()));
+        for key in ["player", "phase"] {
+            if self.state["turn"][key] != first[key] {
+                self.replace(format!("/turn/{}", key), first[key].clone())?;
+            }
+        }
+        self.deal()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine::{scratch_bundle, Match, RejectCode, Tick};
+    use crate::hooks::test_script::{call, module, ROUND_END};
+    use serde_json::json;
+
+    /// `score` passes through `scoring`, whose start ends the round with `winner`.
+    fn table(winner: &str, match_rules: &str) -> Match {
+        let rules = format!(
+            "
+entities:
+  card: {{ kind: card }}
+zones:
+  deck: {{ shape: stack, visibility: none }}
+  hands: {{ shape: list, perPlayer: true, visibility: owner }}
+verbs:
+  draw: {{ effect: [{{ move: {{ from: deck, to: hands, count: 1, playerSlot: actor }} }}] }}
+  score: {{ nextPhase: scoring }}
+phases:
+  - id: turn
+    activePlayer: sequential
+    verbs: [draw, score]
+  - id: scoring
+    verbs: []
+setup:
+  - initZone: {{ zone: deck, entity: card, count: 4 }}
+hooks:
+  decide: {{ on: on_phase_start, phase: scoring }}
+match: {}
+",
+            match_rules
+        );
+        let script = module(&[("decide", call(ROUND_END, Some((0, winner.len()))))], winner.as_bytes());
+        let names = ["ann".to_string(), "bob".to_string()];
+        Match::new(scratch_bundle(&rules, Some(&script)), &names, 1).unwrap()
+    }
+
+    fn play(m: &mut Match, verb: &str) -> Tick {
+        m.apply_verb("p1", &json!({ "verb": verb, "args": {} })).unwrap()
+    }
+
+    #[test]
+    fn a_round_end_scores_and_deals_the_next_round() {
+        let mut m = table("p1", "{ winScore: 2 }");
+        play(&mut m, "draw");
+        let end = play(&mut m, "score").round_end.unwrap();
+        assert_eq!((end.round, &end.winner, &end.scores), (1, &json!("p1"), &json!({ "p1": 1, "p2": 0 })));
+        assert_eq!(end.result, None);
+        assert!(!m.is_over());
+        // a fresh deal: the drawn card is back in a full deck, play restarts with p1
+        assert_eq!(m.round(), 2);
+        assert_eq!(m.state["zones"]["deck"].as_array().unwrap().len(), 4);
+        assert_eq!(m.state["zones"]["hands"]["p1"], json!([]));
+        assert_eq!((m.turn_player(), m.phase_id()), (Some("p1"), "turn"));
+    }
+
+    #[test]
+    fn reaching_win_score_ends_the_match() {
+        let mut m = table("p1", "{ winScore: 2 }");
+        play(&mut m, "score");
+        let end = play(&mut m, "score").round_end.unwrap();
+        let result = json!({ "winner": "p1", "scores": { "p1": 2, "p2": 0 } });
+        assert_eq!(end.result.as_ref(), Some(&result));
+        assert!(m.is_over());
+        assert_eq!(m.state["result"], result);
+        assert_eq!(m.round(), 2, "no round is dealt after the last");
+        let rejection = m.apply_verb("p1", &json!({ "verb": "draw", "args": {} })).unwrap_err();
+        assert_eq!(rejection.code, RejectCode::GameOver);
+    }
+
+    #[test]
+    fn the_rounds_limit_ends_the_match_and_a_tie_is_a_draw() {
+        let mut m = table("draw", "{ rounds: 2 }");
+        assert!(play(&mut m, "score").round_end.unwrap().result.is_none());
+        let end = play(&mut m, "score").round_end.unwrap();
+        assert_eq!(end.winner, json!(null));
+        assert_eq!(end.result, Some(json!({ "winner": null, "scores": { "p1": 0, "p2": 0 } })));
+        assert!(m.is_over());
+    }
+
+    #[test]
+    fn the_rounds_limit_gives_the_match_to_the_leader() {
+        let mut m = table("p2", "{ winScore: 5, rounds: 1 }");
+        let end = play(&mut m, "score").round_end.unwrap();
+        assert_eq!(end.result, Some(json!({ "winner": "p2", "scores": { "p1": 0, "p2": 1 } })));
+    }
+}
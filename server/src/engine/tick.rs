@@ -0,0 +1,250 @@
+//! tick.rs – one verb and everything it triggers, applied all-or-nothing
+//! Effects and hooks run against a working copy of the state. The copy is
+//! committed together with its combined diff, or thrown away and the last
+//! committed state put back.
+
+use super::{rounds::RoundEnd, Match, Rng, State};
+use crate::hooks::{Budget, Snapshot};
+use indexmap::IndexMap;
+use serde_json::{json, Value};
+
+/// A committed tick: the action, its diff and what each recipient may see of it.
+#[derive(Debug)]
+pub struct Tick {
+    /// `turn.tick` after it
+    pub tick: u64,
+    /// position in the match's event stream: 1, 2, 3, … with no gaps
+    pub seq: u64,
+    pub actor: String,
+    pub verb: String,
+    pub args: Value,
+    pub diff: Value,
+    /// seat → that seat's projection of `diff`
+    pub views: IndexMap<String, Value>,
+    /// projection for sockets without a seat
+    pub public: Value,
+    /// digest of the numbers it drew from the match RNG (`Rng::tick_digest`)
+    pub rng_digest: String,
+    /// set when a hook ended the round during it
+    pub round_end: Option<RoundEnd>,
+}
+
+/// What a tick needs to put back if it fails.
+pub(super) struct Checkpoint {
+    state: State,
+    rng: Rng,
+    /// the script instance; hooks may keep state in it between calls
+    hooks: Option<Snapshot>,
+}
+
+impl Match {
+    /// Run `body` as one tick. On success the ops it recorded become the tick's
+    /// diff; on error the match is exactly as it was before.
+    pub(super) fn transaction(&mut self, body: impl FnOnce(&mut Self) -> anyhow::Result<()>) -> anyhow::Result<Tick> {
+        let checkpoint = self.begin();
+        match body(self) {
+            Ok(()) => Ok(self.commit(checkpoint)),
+            Err(e) => {
+                self.rollback(checkpoint);
+                Err(e)
+            }
+        }
+    }
+
+    fn begin(&mut self) -> Checkpoint {
+        self.patch.clear();
+        self.turn_passed = false;
+        self.entered_turn_phase = false;
+        self.round_over = None;
+        self.hook_budget = Budget::start();
+        self.rng.begin_tick();
+        let hooks = self.hooks.as_mut().map(|h| h.snapshot());
+        Checkpoint { state: self.state.clone(), rng: self.rng.clone(), hooks }
+    }
+
+    fn commit(&mut self, checkpoint: Checkpoint) -> Tick {
+        let diff = std::mem::take(&mut self.patch);
+        let before = &checkpoint.state;
+        let views = self.seats().into_iter().map(|s| (s.clone(), self.project_diff(before, &diff, Some(&s)))).collect();
+        let public = self.project_diff(before, &diff, None);
+        Tick {
+            tick: self.tick(),
+            seq: self.seq() + 1,
+            actor: self.invocation["actor"].as_str().unwrap_or_default().to_string(),
+            verb: self.invocation["verb"].as_str().unwrap_or_default().to_string(),
+            args: self.invocation["args"].clone(),
+            diff: Value::Array(diff),
+            views,
+            public,
+            rng_digest: self.rng.tick_digest(),
+            round_end: None,
+        }
+    }
+
+    /// Events committed so far; the `seq` of the latest one.
+    pub fn seq(&self) -> u64 {
+        self.log.events.len() as u64
+    }
+
+    fn rollback(&mut self, checkpoint: Checkpoint) {
+        self.state = checkpoint.state;
+        self.rng = checkpoint.rng;
+        self.patch.clear();
+        // a hook that ran in the failed tick may have changed its instance; a replay
+        // of the log never runs that tick, so neither may anything after it here
+        let bundle = self.bundle.clone();
+        if let (Some(script), Some(snapshot)) = (bundle.script.as_ref(), checkpoint.hooks.as_ref()) {
+            self.hooks = match script.restore(snapshot) {
+                Ok(runtime) => Some(runtime),
+                Err(e) => {
+                    println!("[Hooks] ERROR: {} {}: cannot restore script.wasm after a rejected tick: {:#}",
+                        bundle.game_id, bundle.version, e);
+                    None
+                }
+            };
+        }
+    }
+}
+
+impl Tick {
+    /// The `event` envelope as `seat` receives it (`None`: a socket without a seat).
+    pub fn envelope(&self, seat: Option<&str>) -> Value {
+        json!({
+            "type": "event",
+            "tick": self.tick,
+            "seq": self.seq,
+            "actor": self.actor,
+            "verb": self.verb,
+            "args": self.args,
+            "diff": seat.and_then(|s| self.views.get(s)).unwrap_or(&self.public),
+        })
+    }
+
+    /// How the round ended, if it did during this tick (see `RoundEnd::envelopes`).
+    pub fn round_envelopes(&self) -> Vec<Value> {
+        self.round_end.as_ref().map(|end| end.envelopes(self.tick, self.seq)).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Tick;
+    use crate::engine::{patch, scratch_bundle, Match, RejectCode, Rejection};
+    use crate::hooks::limits;
+    use crate::hooks::test_script::{call, module, spin, COUNT, EMIT, LOOP_FOREVER, SPIN_FUEL, TRAP};
+    use serde_json::json;
+
+    /// `draw` and `spill` shuffle the deck first; `spill` then fails, the pile being empty.
+    const RULES: &str = "
+entities:
+  card: { kind: card }
+zones:
+  deck: { shape: stack, visibility: none }
+  pile: { shape: list, visibility: all }
+  hands: { shape: list, perPlayer: true, visibility: owner }
+verbs:
+  go: {}
+  draw: { effect: [{ shuffle: { zone: deck } }, { move: { from: deck, to: hands, count: 1, playerSlot: actor } }] }
+  spill: { effect: [{ shuffle: { zone: deck } }, { move: { from: pile, to: deck, count: 1 } }] }
+phases:
+  - id: turn
+    activePlayer: sequential
+    verbs: [go, draw, spill]
+setup:
+  - initZone: { zone: deck, entity: card, count: 6 }
+hooks:
+";
+
+    /// Two seats; each `(name, event, body)` hook subscribes to `event`, and
+    /// the script's memory starts with `data`.
+    fn table(hooks: &[(&str, &str, Vec<u8>)], data: &[u8]) -> Match {
+        let mut rules = RULES.to_string();
+        for (name, event, _) in hooks {
+            rules.push_str(&format!("  {}: {}\n", name, event));
+        }
+        let bodies: Vec<(&str, Vec<u8>)> = hooks.iter().map(|(name, _, body)| (*name, body.clone())).collect();
+        let names = ["ann".to_string(), "bob".to_string()];
+        Match::new(scratch_bundle(&rules, Some(&module(&bodies, data))), &names, 1).unwrap()
+    }
+
+    fn play(m: &mut Match, verb: &str) -> Result<Tick, Rejection> {
+        m.apply_verb("p1", &json!({ "verb": verb, "args": {} }))
+    }
+
+    /// The next number the match RNG would draw.
+    fn next_draw(m: &Match) -> u64 {
+        m.rng.clone().next_u64()
+    }
+
+    fn count(m: &mut Match) -> i32 {
+        m.hooks.as_mut().unwrap().global_i32("count").unwrap()
+    }
+
+    #[test]
+    fn a_tick_diff_rebuilds_the_state_it_commits() {
+        let op = br#"{"op":"replace","path":"/scores/p1","value":3}"#;
+        let mut m = table(&[("score", "on_after_play", call(EMIT, Some((0, op.len()))))], op);
+        for verb in ["draw", "go", "draw"] {
+            let before = m.state.clone();
+            let tick = play(&mut m, verb).unwrap();
+            let mut rebuilt = before;
+            for op in tick.diff.as_array().unwrap() {
+                patch::apply(&mut rebuilt, op).unwrap();
+            }
+            assert_eq!(rebuilt, m.state, "`{}`", verb);
+        }
+        assert_eq!(m.state["scores"]["p1"], 3);
+    }
+
+    #[test]
+    fn a_failed_effect_puts_back_the_state_the_rng_and_the_script() {
+        let mut m = table(&[("counter", "on_after_effect", COUNT.to_vec())], &[]);
+        play(&mut m, "draw").unwrap();
+        let (state, draw, counted) = (m.state.clone(), next_draw(&m), count(&mut m));
+        // the shuffle went through and the counter counted it before the move failed
+        assert_eq!(play(&mut m, "spill").unwrap_err().code, RejectCode::EffectFailed);
+        assert_eq!(m.state, state);
+        assert_eq!(next_draw(&m), draw);
+        assert_eq!(count(&mut m), counted);
+        assert_eq!(m.seq(), 1);
+    }
+
+    #[test]
+    fn a_failed_hook_puts_back_the_state_the_rng_and_the_script() {
+        let hooks = [("counter", "on_after_effect", COUNT.to_vec()), ("trap", "on_after_play", TRAP.to_vec())];
+        let mut m = table(&hooks, &[]);
+        let (state, draw) = (m.state.clone(), next_draw(&m));
+        assert_eq!(play(&mut m, "draw").unwrap_err().code, RejectCode::HookFailed);
+        assert_eq!(m.state, state);
+        assert_eq!(next_draw(&m), draw);
+        assert_eq!(count(&mut m), 0);
+    }
+
+    #[test]
+    fn a_hook_that_never_returns_fails_the_verb_and_changes_nothing() {
+        let mut m = table(&[("forever", "on_after_play", LOOP_FOREVER.to_vec())], &[]);
+        let before = m.state.clone();
+        let rejection = play(&mut m, "go").unwrap_err();
+        assert_eq!(rejection.code, RejectCode::HookFailed);
+        assert_eq!(m.state, before);
+        assert_eq!(m.seq(), 0);
+    }
+
+    #[test]
+    fn the_hooks_of_one_tick_share_one_budget() {
+        // each hook alone fits in the budget, both together do not
+        let rounds = (limits().fuel * 6 / 10 / SPIN_FUEL) as i32;
+        let mut alone = table(&[("first", "on_after_play", spin(rounds))], &[]);
+        play(&mut alone, "go").unwrap();
+        // and every tick starts with the whole budget again
+        play(&mut alone, "go").unwrap();
+
+        let both = [("first", "on_after_play", spin(rounds)), ("second", "on_after_play", spin(rounds))];
+        let mut m = table(&both, &[]);
+        let before = m.state.clone();
+        let rejection = play(&mut m, "go").unwrap_err();
+        assert_eq!(rejection.code, RejectCode::HookFailed);
+        assert!(rejection.reason.contains("hook `second` ran out of fuel"), "{}", rejection.reason);
+        assert_eq!(m.state, before);
+    }
+}
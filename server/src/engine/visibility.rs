@@ -0,0 +1,208 @@
+//! visibility.rs – what each seat may see of the state and of a tick's diff
+//! Zone `visibility` decides which entities a seat sees. Those it sees are
+//! listed by handle (opaque, see handles.rs) and have their `entities` entry;
+//! any other one shows as `HIDDEN` wherever it sits, so a face-down deck is a
+//! row of placeholders and a seat learns a handle only once it can see the entity.
+//! How a pending question resumes (`pending.resume`) is seen by nobody.
+
+use super::{patch, Match, State};
+use crate::entities::{Visibility, ZoneShape};
+use serde_json::{json, Map, Value};
+use std::collections::HashSet;
+
+/// Stands in for the handle of an entity the recipient can't see.
+pub const HIDDEN: &str = "?";
+
+impl Match {
+    /// The current state as `seat` may see it; `None` for a socket without a seat.
+    pub fn view(&self, seat: Option<&str>) -> Value {
+        self.project(&self.state, seat)
+    }
+
+    pub(super) fn project(&self, state: &State, seat: Option<&str>) -> Value {
+        let mut view = state.clone();
+        let mut shown = HashSet::new();
+        if let Some(zones) = view["zones"].as_object() {
+            for (id, zone) in zones {
+                let template = self.bundle.rules.zones.get(id);
+                // the implicit bank is a face-down supply
+                let visibility = template.map_or(Visibility::None, |z| z.visibility);
+                let stack = template.is_some_and(|z| z.shape == ZoneShape::Stack);
+                match (template.is_some_and(|z| z.per_player), zone) {
+                    (true, Value::Object(slots)) => {
+                        for (slot, pile) in slots {
+                            let owner = Some(slot.as_str()) == seat;
+                            shown_in(pile, visibility, owner, stack, &mut shown);
+                        }
+                    }
+                    (_, pile) => shown_in(pile, visibility, false, stack, &mut shown),
+                }
+            }
+        }
+        let hidden = |id: &str| state["entities"].get(id).is_some() && !shown.contains(id);
+        if let Some(sections) = view.as_object_mut() {
+            for (_, section) in sections.iter_mut().filter(|(key, _)| *key != "entities") {
+                mask(section, &hidden);
+            }
+        }
+        if let Some(entities) = view["entities"].as_object_mut() {
+            entities.retain(|id, _| shown.contains(id));
+        }
+        if let Some(pending) = view.get_mut("pending").and_then(Value::as_object_mut) {
+            pending.remove("resume");
+        }
+        view
+    }
+
+    /// `diff` (taking `before` to the current state) as `seat` may see it. Replaying
+    /// the result on `project(before, seat)` gives exactly `view(seat)`: `entities`
+    /// ops for what the seat can't see are dropped, handles it can't see are masked
+    /// in the rest, and whatever that leaves out of date (an entity turning face up,
+    /// a new top card) is patched up at the end. `pending` is only ever sent whole.
+    pub(super) fn project_diff(&self, before: &State, diff: &[Value], seat: Option<&str>) -> Value {
+        let mut replay = self.project(before, seat);
+        let target = self.view(seat);
+        let shown = target["entities"].as_object().cloned().unwrap_or_default();
+        // ids of entities on either side of the tick, to tell handles from other strings
+        let known = |id: &str| self.state["entities"].get(id).is_some() || before["entities"].get(id).is_some();
+        let hidden = |id: &str| known(id) && !shown.contains_key(id);
+
+        let mut ops = Vec::new();
+        for op in diff {
+            if !concerns_shown(op, &shown) {
+                continue;
+            }
+            let mut op = op.clone();
+            if let Some(value) = op.get_mut("value") {
+                mask(value, &hidden);
+            }
+            if patch::apply(&mut replay, &op).is_err() {
+                return json!([{ "op": "replace", "path": "", "value": target }]);
+            }
+            ops.push(op);
+        }
+        for section in ["zones", "entities"] {
+            let path = format!("/{}", section);
+            sync_entries(&path, &replay[section], &target[section], &[], &mut ops);
+        }
+        // anything else still out of date (`pending`, whose ops are never sent, or
+        // a value holding a handle masked since) is replaced whole
+        sync_entries("", &replay, &target, &["zones", "entities"], &mut ops);
+        Value::Array(ops)
+    }
+}
+
+/// Ops under `prefix` that turn object `have` into `want`, one whole entry at a
+/// time, leaving out the `skip` keys.
+fn sync_entries(prefix: &str, have: &Value, want: &Value, skip: &[&str], ops: &mut Vec<Value>) {
+    if have == want {
+        return;
+    }
+    let empty = Map::new();
+    let (have, want) = (have.as_object().unwrap_or(&empty), want.as_object().unwrap_or(&empty));
+    for (key, value) in want.iter().filter(|(k, _)| !skip.contains(&k.as_str())) {
+        let path = format!("{}/{}", prefix, patch::escape(key));
+        match have.get(key) {
+            None => ops.push(json!({ "op": "add", "path": path, "value": value })),
+            Some(old) if old != value => ops.push(json!({ "op": "replace", "path": path, "value": value })),
+            Some(_) => {}
+        }
+    }
+    for key in have.keys().filter(|k| !want.contains_key(*k) && !skip.contains(&k.as_str())) {
+        ops.push(json!({ "op": "remove", "path": format!("{}/{}", prefix, patch::escape(key)) }));
+    }
+}
+
+/// Collect the ids in one pile (or grid) the recipient may see.
+/// `topPublic` shows index 0 of a stack, the last item of anything else.
+fn shown_in(pile: &Value, visibility: Visibility, owner: bool, stack: bool, shown: &mut HashSet<String>) {
+    let Value::Array(items) = pile else { return };
+    let len = items.len();
+    for (index, item) in items.iter().enumerate() {
+        let visible = match visibility {
+            Visibility::All => true,
+            Visibility::None => false,
+            Visibility::Owner => owner,
+            Visibility::TopPublic => owner || index == if stack { 0 } else { len - 1 },
+        };
+        match item {
+            Value::Array(_) => shown_in(item, visibility, owner, stack, shown),
+            Value::String(id) if visible => {
+                shown.insert(id.clone());
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Replace every handle in `value` that `hidden` says the recipient can't see.
+fn mask(value: &mut Value, hidden: &dyn Fn(&str) -> bool) {
+    match value {
+        Value::String(id) if hidden(id) => *value = json!(HIDDEN),
+        Value::Array(items) => items.iter_mut().for_each(|item| mask(item, hidden)),
+        Value::Object(fields) => fields.values_mut().for_each(|field| mask(field, hidden)),
+        _ => {}
+    }
+}
+
+/// False for an op on the `entities` entry of something the recipient can't see,
+/// and for any op on `pending`.
+fn concerns_shown(op: &Value, shown: &Map<String, Value>) -> bool {
+    let path = op["path"].as_str().unwrap_or_default();
+    if path == "/pending" || path.starts_with("/pending/") {
+        return false;
+    }
+    match path.strip_prefix("/entities/") {
+        Some(id) => shown.contains_key(&patch::unescape(id)),
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::test_match;
+
+    #[test]
+    fn a_drawn_card_is_only_revealed_to_its_owner() {
+        let mut game = test_match("love-letter", 2, 7);
+        let drawer = game.turn_player().unwrap().to_string();
+        let other = game.seats().into_iter().find(|s| *s != drawer).unwrap();
+        let mut views: Vec<Value> = [&drawer, &other].iter().map(|s| game.view(Some(s.as_str()))).collect();
+
+        let tick = game.apply_verb(&drawer, &json!({ "verb": "draw", "args": {} })).expect("draw is legal");
+
+        let entity_ops = |seat: &str| {
+            let ops = tick.views[seat].as_array().unwrap();
+            ops.iter().filter(|op| op["path"].as_str().unwrap().starts_with("/entities/")).count()
+        };
+        assert!(entity_ops(&drawer) > 0, "the drawer learns the card");
+        assert_eq!(entity_ops(&other), 0, "the other seat gets no `entities` entry");
+        let added = tick.views[other.as_str()]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|op| op["op"] == "add" && op["path"].as_str().unwrap().starts_with("/zones/hands/"))
+            .map(|op| op["value"].clone());
+        assert_eq!(added, Some(json!(HIDDEN)), "nor the card's handle");
+
+        // each projected diff takes that seat's old view to its new one
+        for (view, seat) in views.iter_mut().zip([&drawer, &other]) {
+            for op in tick.views[seat.as_str()].as_array().unwrap() {
+                patch::apply(view, op).unwrap();
+            }
+            assert_eq!(*view, game.view(Some(seat.as_str())));
+        }
+    }
+
+    #[test]
+    fn face_down_zones_show_placeholders() {
+        let game = test_match("love-letter", 2, 7);
+        let view = game.view(Some("p1"));
+        let deck = view["zones"]["deck"].as_array().unwrap();
+        assert!(!deck.is_empty());
+        assert!(deck.iter().all(|id| id == HIDDEN));
+        assert_eq!(view["zones"]["burn"], json!([HIDDEN]));
+        assert_eq!(view["entities"], json!({}));
+    }
+}
@@ -0,0 +1,969 @@
+//! entities.rs – typed model of a bundle's `entities.yaml` (RFC 0001 top-level keys)
+//! entities / zones / verbs / phases / setup / hooks
+
+use anyhow::anyhow;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::path::Path;
+
+/// Everything declared in `entities.yaml`. Map sections keep their YAML order.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rules {
+    #[serde(default)]
+    pub entities: IndexMap<String, EntityTemplate>,
+    #[serde(default)]
+    pub zones: IndexMap<String, ZoneTemplate>,
+    #[serde(default)]
+    pub verbs: IndexMap<String, VerbTemplate>,
+    #[serde(default)]
+    pub phases: Vec<PhaseTemplate>,
+    #[serde(default)]
+    pub setup: Vec<SetupStep>,
+    /// hook function name → lifecycle event it subscribes to
+    #[serde(default)]
+    pub hooks: IndexMap<String, HookSubscription>,
+    /// how rounds add up to a match; a single round when absent
+    #[serde(default, rename = "match")]
+    pub match_rules: MatchRules,
+}
+
+/* --------------------------------------------------------------------------
+   templates
+   ----------------------------------------------------------------------- */
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct EntityTemplate {
+    pub kind: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<i64>,
+    /// copies of this entity in a fresh match
+    #[serde(default = "default_count")]
+    pub count: u32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// hook fired when the entity is played; `none` means no hook
+    #[serde(default, deserialize_with = "hook_ref", skip_serializing_if = "Option::is_none")]
+    pub hook: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ui: Option<Ui>,
+    /// any other key (e.g. `glyph`)
+    #[serde(flatten)]
+    pub props: serde_json::Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ZoneTemplate {
+    pub shape: ZoneShape,
+    pub visibility: Visibility,
+    /// false → entities can't leave (e.g. a discard log)
+    #[serde(default = "default_true")]
+    pub mutable: bool,
+    /// one instance of the zone per seated player
+    #[serde(default)]
+    pub per_player: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ZoneShape {
+    Stack,
+    Queue,
+    Bag,
+    List,
+    Grid,
+    Flag,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Visibility {
+    None,
+    Owner,
+    All,
+    TopPublic,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct VerbTemplate {
+    /// param name → declared type (`u8`, `Id`, `PlayerId`, …)
+    #[serde(default)]
+    pub params: IndexMap<String, ParamSpec>,
+    #[serde(default)]
+    pub pre: Vec<Call>,
+    #[serde(default)]
+    pub effect: Vec<Call>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_phase: Option<String>,
+    /// `$param` naming the entity the verb plays, for verbs with several `Id` params
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entity: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ui: Option<Ui>,
+}
+
+impl VerbTemplate {
+    /// The param holding the entity this verb plays, whose `hook:` then fires:
+    /// the one named by `entity:`, or else the verb's only `Id` param.
+    pub fn played_param(&self) -> Option<&str> {
+        if let Some(entity) = &self.entity {
+            return entity.strip_prefix('$');
+        }
+        let mut ids = self.params.iter().filter(|(_, spec)| matches!(spec.ty, ParamType::Id { .. }));
+        match (ids.next(), ids.next()) {
+            (Some((name, _)), None) => Some(name),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PhaseTemplate {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_player: Option<ActivePlayer>,
+    #[serde(default)]
+    pub verbs: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ActivePlayer {
+    Sequential,
+    Simultaneous,
+}
+
+/// `match:` – when a match is over. Each round's winner scores a point; the
+/// match ends once someone reaches `winScore` or after `rounds` rounds.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct MatchRules {
+    /// points that win the match: one number, or one per player count (`{ 2: 7, 3: 5 }`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub win_score: Option<WinScore>,
+    /// the match ends after this many rounds; the highest score wins
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rounds: Option<u32>,
+}
+
+impl Default for MatchRules {
+    fn default() -> Self {
+        Self { win_score: None, rounds: Some(1) }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum WinScore {
+    Points(u32),
+    /// player count → points
+    ByPlayers(IndexMap<u32, u32>),
+}
+
+impl MatchRules {
+    /// Points that win a match of `players` players, if there is such a target.
+    pub fn target_score(&self, players: usize) -> Option<u32> {
+        match self.win_score.as_ref()? {
+            WinScore::Points(points) => Some(*points),
+            WinScore::ByPlayers(table) => table.get(&(players as u32)).copied(),
+        }
+    }
+}
+
+/// Declared type of a verb param. Short form: `u8` … `i64`, `bool`, `string`,
+/// `PlayerId` or `Id`, with a trailing `?` when the param may be left out, or a
+/// list of strings for an enum. Long form: `{ type: u8, min: 0, max: 2 }`,
+/// `{ type: Id, zone: hands }`, `{ type: enum, values: [...] }`, each taking
+/// `optional: true`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParamSpec {
+    pub ty: ParamType,
+    pub optional: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParamType {
+    /// `u8` … `i64`; `min`/`max` narrow the type's own range
+    Int { name: String, min: Option<i64>, max: Option<i64> },
+    Bool,
+    String,
+    /// a seat id (`p1`, `p2`, …)
+    PlayerId,
+    /// an entity handle; with `zone`, one in that zone (the actor's own slot of a per-player zone)
+    Id { zone: Option<String> },
+    /// one of the listed strings
+    Enum(Vec<String>),
+}
+
+/// Integer param types and their ranges (`u64` stops at `i64::MAX`).
+const INT_TYPES: &[(&str, i64, i64)] = &[
+    ("u8", 0, u8::MAX as i64),
+    ("u16", 0, u16::MAX as i64),
+    ("u32", 0, u32::MAX as i64),
+    ("u64", 0, i64::MAX),
+    ("i8", i8::MIN as i64, i8::MAX as i64),
+    ("i16", i16::MIN as i64, i16::MAX as i64),
+    ("i32", i32::MIN as i64, i32::MAX as i64),
+    ("i64", i64::MIN, i64::MAX),
+];
+
+impl ParamType {
+    /// Values an `Int` accepts: its `min`/`max`, else the bounds of its type.
+    pub fn int_range(&self) -> Option<(i64, i64)> {
+        let ParamType::Int { name, min, max } = self else { return None };
+        let (_, lo, hi) = INT_TYPES.iter().find(|(n, _, _)| n == name)?;
+        Some((min.unwrap_or(*lo), max.unwrap_or(*hi)))
+    }
+}
+
+/// Lifecycle events a hook can subscribe to (`on_after_play`, …).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum HookEvent {
+    #[serde(rename = "on_after_play")]
+    AfterPlay,
+    #[serde(rename = "on_phase_start")]
+    PhaseStart,
+    #[serde(rename = "on_phase_end")]
+    PhaseEnd,
+    #[serde(rename = "on_after_effect")]
+    AfterEffect,
+    /// never raised; names the `then` hook an `ask` resumes with the answer
+    #[serde(rename = "on_answer")]
+    Answer,
+}
+
+/// A `hooks:` entry: `win_check: on_phase_end`, or for a phase event limited
+/// to one phase, `win_hook: { on: on_phase_start, phase: checkWin }`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HookSubscription {
+    pub on: HookEvent,
+    pub phase: Option<String>,
+}
+
+impl HookSubscription {
+    /// Whether the hook runs for `event` raised in `phase`.
+    pub fn runs_on(&self, event: HookEvent, phase: &str) -> bool {
+        self.on == event && self.phase.as_deref().is_none_or(|p| p == phase)
+    }
+}
+
+/// Client-facing hints. Unknown keys are passed through untouched.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Ui {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub help: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub picker: Option<String>,
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub param_prompts: IndexMap<String, String>,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, Value>,
+}
+
+/// A named predicate or effect written as `{ name: { arg: value, … } }` or a bare `name`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Call {
+    pub name: String,
+    pub args: serde_json::Map<String, Value>,
+}
+
+/// One `setup:` entry.
+#[derive(Debug, Clone)]
+pub enum SetupStep {
+    /// `repeat: { times: n, actions: [...] }`
+    Repeat { times: u32, actions: Vec<SetupStep> },
+    /// `forEachPlayer: [...]` – run the nested steps once per seated player
+    ForEachPlayer(Vec<SetupStep>),
+    /// bare `draw` – run the named verb's effects
+    Verb(String),
+    /// any other `{ name: args }` effect
+    Effect(Call),
+}
+
+fn default_count() -> u32 {
+    1
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn hook_ref<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    let name = Option::<String>::deserialize(deserializer)?;
+    Ok(name.filter(|n| n != "none"))
+}
+
+/* --------------------------------------------------------------------------
+   loading + validation
+   ----------------------------------------------------------------------- */
+
+impl Rules {
+    /// Read and validate `entities.yaml`. Errors name the file, the YAML
+    /// line and the key path of the offending entry.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("cannot read {}: {}", path.display(), e))?;
+        Self::parse(&text).map_err(|e| e.into_anyhow(path, &text))
+    }
+
+    fn parse(text: &str) -> Result<Self, RulesError> {
+        let de = serde_yaml::Deserializer::from_str(text);
+        let rules: Rules = serde_path_to_error::deserialize(de).map_err(|e| {
+            let path = e
+                .path()
+                .iter()
+                .filter_map(|seg| match seg {
+                    serde_path_to_error::Segment::Map { key } => Some(Seg::Key(key.clone())),
+                    serde_path_to_error::Segment::Seq { index } => Some(Seg::Index(*index)),
+                    _ => None,
+                })
+                .collect();
+            let inner = e.into_inner();
+            // serde_yaml renders "<path>: <message> at line L column C"; keep just the message
+            let mut message = inner.to_string();
+            if let Some(loc) = inner.location() {
+                let suffix = format!(" at line {} column {}", loc.line(), loc.column());
+                message.truncate(message.strip_suffix(&suffix).map_or(message.len(), str::len));
+            }
+            if let Some((prefix, rest)) = message.split_once(": ") {
+                if !prefix.contains(' ') {
+                    message = rest.to_string();
+                }
+            }
+            RulesError { path, line: inner.location().map(|l| l.line()), message }
+        })?;
+        rules.validate()?;
+        Ok(rules)
+    }
+
+    /// Cross-reference checks serde can't express.
+    fn validate(&self) -> Result<(), RulesError> {
+        for (id, zone) in &self.zones {
+            let has_dims = zone.width.is_some() || zone.height.is_some();
+            match zone.shape {
+                ZoneShape::Grid if zone.width.unwrap_or(0) == 0 || zone.height.unwrap_or(0) == 0 => {
+                    return Err(RulesError::at(
+                        [key("zones"), key(id)],
+                        "grid zones need a non-zero width and height",
+                    ));
+                }
+                ZoneShape::Grid => {}
+                _ if has_dims => {
+                    return Err(RulesError::at(
+                        [key("zones"), key(id), key(if zone.width.is_some() { "width" } else { "height" })],
+                        "width/height only apply to `shape: grid`",
+                    ));
+                }
+                _ => {}
+            }
+        }
+
+        for (i, phase) in self.phases.iter().enumerate() {
+            if self.phases[..i].iter().any(|p| p.id == phase.id) {
+                return Err(RulesError::at([key("phases"), Seg::Index(i)], format!("duplicate phase id `{}`", phase.id)));
+            }
+            if phase.active_player == Some(ActivePlayer::Simultaneous) {
+                return Err(RulesError::at(
+                    [key("phases"), Seg::Index(i), key("activePlayer")],
+                    "simultaneous phases are out of scope for spec 0.1",
+                ));
+            }
+            if let Some(verb) = phase.verbs.iter().find(|v| !self.verbs.contains_key(*v)) {
+                return Err(RulesError::at(
+                    [key("phases"), Seg::Index(i), key("verbs")],
+                    format!("phase `{}` lists unknown verb `{}`", phase.id, verb),
+                ));
+            }
+        }
+
+        for (id, verb) in &self.verbs {
+            if id == crate::engine::ANSWER {
+                return Err(RulesError::at([key("verbs"), key(id)], "`answer` is reserved for answering an `ask`"));
+            }
+            for (param, spec) in &verb.params {
+                if let ParamType::Id { zone: Some(zone) } = &spec.ty {
+                    if zone != crate::engine::BANK && !self.zones.contains_key(zone) {
+                        return Err(RulesError::at(
+                            [key("verbs"), key(id), key("params"), key(param), key("zone")],
+                            format!("unknown zone `{}`", zone),
+                        ));
+                    }
+                }
+            }
+            let ids: Vec<&str> = verb
+                .params
+                .iter()
+                .filter(|(_, spec)| matches!(spec.ty, ParamType::Id { .. }))
+                .map(|(name, _)| name.as_str())
+                .collect();
+            match &verb.entity {
+                Some(entity) if !entity.strip_prefix('$').is_some_and(|name| ids.contains(&name)) => {
+                    return Err(RulesError::at(
+                        [key("verbs"), key(id), key("entity")],
+                        format!("`{}` is not an `Id` param of this verb (write `$param`)", entity),
+                    ));
+                }
+                None if ids.len() > 1 => {
+                    return Err(RulesError::at(
+                        [key("verbs"), key(id), key("params")],
+                        format!(
+                            "several `Id` params ({}); say which entity the verb plays with `entity: $param`",
+                            ids.iter().map(|name| format!("`{}`", name)).collect::<Vec<_>>().join(", ")
+                        ),
+                    ));
+                }
+                _ => {}
+            }
+            for (i, call) in verb.pre.iter().enumerate() {
+                if let Err(e) = crate::engine::check_precondition(call, self, verb) {
+                    let mut path = vec![key("verbs"), key(id), key("pre"), Seg::Index(i), key(&call.name)];
+                    path.extend(e.arg.as_deref().map(key));
+                    return Err(RulesError::at(path, e.message));
+                }
+            }
+            for (i, call) in verb.effect.iter().enumerate() {
+                if let Err(e) = crate::engine::check_effect(call, self, Some(verb)) {
+                    let mut path = vec![key("verbs"), key(id), key("effect"), Seg::Index(i), key(&call.name)];
+                    path.extend(e.arg.as_deref().map(key));
+                    return Err(RulesError::at(path, e.message));
+                }
+            }
+            if let Some(next) = &verb.next_phase {
+                if self.phase(next).is_none() {
+                    return Err(RulesError::at(
+                        [key("verbs"), key(id), key("nextPhase")],
+                        format!("unknown phase `{}`", next),
+                    ));
+                }
+            }
+        }
+
+        self.validate_setup(&self.setup, vec![key("setup")])?;
+
+        let rules = &self.match_rules;
+        if rules.win_score.is_none() && rules.rounds.is_none() {
+            return Err(RulesError::at([key("match")], "`match` needs `winScore`, `rounds` or both"));
+        }
+        if rules.rounds == Some(0) {
+            return Err(RulesError::at([key("match"), key("rounds")], "a match has at least one round"));
+        }
+        let zero_target = match &rules.win_score {
+            Some(WinScore::Points(points)) => *points == 0,
+            Some(WinScore::ByPlayers(table)) => table.values().any(|p| *p == 0),
+            None => false,
+        };
+        if zero_target {
+            return Err(RulesError::at([key("match"), key("winScore")], "a match is won with at least one point"));
+        }
+
+        for (name, hook) in &self.hooks {
+            let Some(phase) = &hook.phase else { continue };
+            if !matches!(hook.on, HookEvent::PhaseStart | HookEvent::PhaseEnd) {
+                return Err(RulesError::at(
+                    [key("hooks"), key(name), key("phase")],
+                    "only `on_phase_start` and `on_phase_end` hooks can be limited to a phase",
+                ));
+            }
+            if self.phase(phase).is_none() {
+                return Err(RulesError::at([key("hooks"), key(name), key("phase")], format!("unknown phase `{}`", phase)));
+            }
+        }
+
+        for (id, entity) in &self.entities {
+            if let Some(hook) = &entity.hook {
+                if !self.hooks.contains_key(hook) {
+                    return Err(RulesError::at(
+                        [key("entities"), key(id), key("hook")],
+                        format!("hook `{}` is not declared under `hooks:`", hook),
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn validate_setup(&self, steps: &[SetupStep], path: Vec<Seg>) -> Result<(), RulesError> {
+        for (i, step) in steps.iter().enumerate() {
+            let mut here = path.clone();
+            here.push(Seg::Index(i));
+            match step {
+                SetupStep::Repeat { actions, .. } => {
+                    here.extend([key("repeat"), key("actions")]);
+                    self.validate_setup(actions, here)?;
+                }
+                SetupStep::ForEachPlayer(actions) => {
+                    here.push(key("forEachPlayer"));
+                    self.validate_setup(actions, here)?;
+                }
+                SetupStep::Verb(name) if !self.verbs.contains_key(name) => {
+                    return Err(RulesError::at(here, format!("`{}` is neither a verb nor an effect", name)));
+                }
+                SetupStep::Verb(_) => {}
+                SetupStep::Effect(call) => {
+                    if let Err(e) = crate::engine::check_effect(call, self, None) {
+                        here.push(key(&call.name));
+                        here.extend(e.arg.as_deref().map(key));
+                        return Err(RulesError::at(here, e.message));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn phase(&self, id: &str) -> Option<&PhaseTemplate> {
+        self.phases.iter().find(|p| p.id == id)
+    }
+}
+
+/* --------------------------------------------------------------------------
+   Call / SetupStep (de)serialization
+   ----------------------------------------------------------------------- */
+
+impl Call {
+    fn from_yaml(value: serde_yaml::Value) -> Result<Self, String> {
+        match value {
+            serde_yaml::Value::String(name) => Ok(Call { name, args: Default::default() }),
+            serde_yaml::Value::Mapping(map) if map.len() == 1 => {
+                let (name, args) = map.into_iter().next().unwrap();
+                let name = name.as_str().ok_or("call name must be a string")?.to_string();
+                let args = match args {
+                    serde_yaml::Value::Null => Default::default(),
+                    serde_yaml::Value::Mapping(_) => serde_yaml::from_value(args)
+                        .map_err(|e| format!("arguments of `{}`: {}", name, e))?,
+                    _ => return Err(format!("arguments of `{}` must be a mapping", name)),
+                };
+                Ok(Call { name, args })
+            }
+            _ => Err("expected `name` or a single-key mapping `{ name: { args } }`".to_string()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Call {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Call::from_yaml(serde_yaml::Value::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+    }
+}
+
+impl Serialize for Call {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry(&self.name, &self.args)?;
+        map.end()
+    }
+}
+
+impl ParamSpec {
+    fn from_yaml(value: serde_yaml::Value) -> Result<Self, String> {
+        use serde_yaml::Value as Yaml;
+        let strings = |value: Yaml| -> Result<Vec<String>, String> {
+            let values: Vec<String> = serde_yaml::from_value(value).map_err(|_| "enum values must be strings")?;
+            if values.is_empty() {
+                return Err("an enum needs at least one value".to_string());
+            }
+            Ok(values)
+        };
+        match value {
+            Yaml::String(short) => {
+                let (name, optional) = match short.strip_suffix('?') {
+                    Some(name) => (name, true),
+                    None => (short.as_str(), false),
+                };
+                if name == "enum" {
+                    return Err("`enum` needs its values: `[a, b]` or `{ type: enum, values: [a, b] }`".to_string());
+                }
+                Ok(ParamSpec { ty: ParamType::named(name)?, optional })
+            }
+            Yaml::Sequence(_) => Ok(ParamSpec { ty: ParamType::Enum(strings(value)?), optional: false }),
+            Yaml::Mapping(map) => {
+                let mut long = IndexMap::new();
+                for (k, v) in map {
+                    let k = k.as_str().ok_or("param keys must be strings")?.to_string();
+                    if !["type", "min", "max", "zone", "values", "optional"].contains(&k.as_str()) {
+                        return Err(format!("unknown key `{}` (expected: type, min, max, zone, values, optional)", k));
+                    }
+                    long.insert(k, v);
+                }
+                let name = long.get("type").and_then(Yaml::as_str).ok_or("a param needs a `type`")?;
+                let mut ty = if name == "enum" { ParamType::Enum(Vec::new()) } else { ParamType::named(name)? };
+                let only = |key: &str, kind: &str| format!("`{}` only applies to {} params", key, kind);
+                let int = |key: &str| -> Result<Option<i64>, String> {
+                    long.get(key).map(|v| v.as_i64().ok_or(format!("`{}` must be an integer", key))).transpose()
+                };
+                match &mut ty {
+                    ParamType::Int { name, min, max } => {
+                        (*min, *max) = (int("min")?, int("max")?);
+                        let (_, lo, hi) = INT_TYPES.iter().find(|(n, _, _)| n == name).copied().unwrap_or_default();
+                        let (from, to) = (min.unwrap_or(lo), max.unwrap_or(hi));
+                        if from < lo || to > hi || from > to {
+                            return Err(format!("{}..={} is not a range within `{}` ({}..={})", from, to, name, lo, hi));
+                        }
+                    }
+                    _ if long.contains_key("min") || long.contains_key("max") => return Err(only("min`/`max", "integer")),
+                    ParamType::Id { zone } => {
+                        *zone = long.get("zone").map(|z| z.as_str().map(str::to_string).ok_or("`zone` must be a zone id")).transpose()?;
+                    }
+                    ParamType::Enum(values) => {
+                        *values = strings(long.get("values").cloned().ok_or("`type: enum` needs `values`")?)?;
+                    }
+                    _ => {}
+                }
+                if long.contains_key("zone") && !matches!(ty, ParamType::Id { .. }) {
+                    return Err(only("zone", "`Id`"));
+                }
+                if long.contains_key("values") && !matches!(ty, ParamType::Enum(_)) {
+                    return Err(only("values", "`enum`"));
+                }
+                let optional = match long.get("optional") {
+                    None => false,
+                    Some(v) => v.as_bool().ok_or("`optional` must be true or false")?,
+                };
+                Ok(ParamSpec { ty, optional })
+            }
+            _ => Err("expected a type name, a list of enum values or `{ type: ... }`".to_string()),
+        }
+    }
+}
+
+impl ParamType {
+    fn named(name: &str) -> Result<Self, String> {
+        match name {
+            _ if INT_TYPES.iter().any(|(n, _, _)| *n == name) => Ok(ParamType::Int { name: name.to_string(), min: None, max: None }),
+            "bool" => Ok(ParamType::Bool),
+            "string" => Ok(ParamType::String),
+            "PlayerId" => Ok(ParamType::PlayerId),
+            "Id" => Ok(ParamType::Id { zone: None }),
+            _ => Err(format!(
+                "unknown param type `{}` (known: {}, bool, string, PlayerId, Id, enum)",
+                name,
+                INT_TYPES.iter().map(|(n, _, _)| *n).collect::<Vec<_>>().join(", ")
+            )),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ParamSpec {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        ParamSpec::from_yaml(serde_yaml::Value::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Written back in the short form whenever it says everything.
+impl Serialize for ParamSpec {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let optional = if self.optional { "?" } else { "" };
+        let mut long = serde_json::Map::new();
+        match &self.ty {
+            ParamType::Int { name, min: None, max: None } => return serializer.serialize_str(&format!("{}{}", name, optional)),
+            ParamType::Bool => return serializer.serialize_str(&format!("bool{}", optional)),
+            ParamType::String => return serializer.serialize_str(&format!("string{}", optional)),
+            ParamType::PlayerId => return serializer.serialize_str(&format!("PlayerId{}", optional)),
+            ParamType::Id { zone: None } => return serializer.serialize_str(&format!("Id{}", optional)),
+            ParamType::Enum(values) if !self.optional => return values.serialize(serializer),
+            ParamType::Int { name, min, max } => {
+                long.insert("type".into(), name.as_str().into());
+                long.extend(min.map(|m| ("min".to_string(), m.into())));
+                long.extend(max.map(|m| ("max".to_string(), m.into())));
+            }
+            ParamType::Id { zone } => {
+                long.insert("type".into(), "Id".into());
+                long.insert("zone".into(), zone.clone().into());
+            }
+            ParamType::Enum(values) => {
+                long.insert("type".into(), "enum".into());
+                long.insert("values".into(), values.clone().into());
+            }
+        }
+        if self.optional {
+            long.insert("optional".into(), true.into());
+        }
+        long.serialize(serializer)
+    }
+}
+
+impl SetupStep {
+    fn from_yaml(value: serde_yaml::Value) -> Result<Self, String> {
+        if let serde_yaml::Value::String(verb) = value {
+            return Ok(SetupStep::Verb(verb));
+        }
+        let name = value.as_mapping().and_then(|m| m.keys().next()).and_then(|k| k.as_str()).unwrap_or("");
+        let body = value.as_mapping().and_then(|m| m.values().next()).cloned().unwrap_or_default();
+        match name {
+            "repeat" => {
+                let times = body
+                    .get("times")
+                    .and_then(|t| t.as_u64())
+                    .ok_or("`repeat` needs an integer `times`")? as u32;
+                let actions = Self::list(body.get("actions").cloned().ok_or("`repeat` needs `actions`")?)?;
+                Ok(SetupStep::Repeat { times, actions })
+            }
+            "forEachPlayer" => Ok(SetupStep::ForEachPlayer(Self::list(body)?)),
+            _ => Ok(SetupStep::Effect(Call::from_yaml(value)?)),
+        }
+    }
+
+    fn list(value: serde_yaml::Value) -> Result<Vec<SetupStep>, String> {
+        match value {
+            serde_yaml::Value::Sequence(items) => items.into_iter().map(Self::from_yaml).collect(),
+            _ => Err("expected a list of setup steps".to_string()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for SetupStep {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        SetupStep::from_yaml(serde_yaml::Value::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+    }
+}
+
+impl Serialize for SetupStep {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            SetupStep::Repeat { times, actions } => {
+                serde_json::json!({ "repeat": { "times": times, "actions": actions } }).serialize(serializer)
+            }
+            SetupStep::ForEachPlayer(steps) => serde_json::json!({ "forEachPlayer": steps }).serialize(serializer),
+            SetupStep::Verb(name) => serializer.serialize_str(name),
+            SetupStep::Effect(call) => call.serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for HookSubscription {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Long {
+            on: HookEvent,
+            phase: Option<String>,
+        }
+        match serde_yaml::Value::deserialize(deserializer)? {
+            value @ serde_yaml::Value::String(_) => {
+                let on = serde_yaml::from_value(value).map_err(serde::de::Error::custom)?;
+                Ok(Self { on, phase: None })
+            }
+            value => {
+                let Long { on, phase } = serde_yaml::from_value(value).map_err(serde::de::Error::custom)?;
+                Ok(Self { on, phase })
+            }
+        }
+    }
+}
+
+impl Serialize for HookSubscription {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match &self.phase {
+            None => self.on.serialize(serializer),
+            Some(phase) => serde_json::json!({ "on": self.on, "phase": phase }).serialize(serializer),
+        }
+    }
+}
+
+/* --------------------------------------------------------------------------
+   errors with YAML line numbers
+   ----------------------------------------------------------------------- */
+
+#[derive(Debug, Clone)]
+enum Seg {
+    Key(String),
+    Index(usize),
+}
+
+fn key(k: &str) -> Seg {
+    Seg::Key(k.to_string())
+}
+
+#[derive(Debug)]
+struct RulesError {
+    path: Vec<Seg>,
+    /// 1-based line reported by the YAML parser, if it had one
+    line: Option<usize>,
+    message: String,
+}
+
+impl RulesError {
+    fn at(path: impl IntoIterator<Item = Seg>, message: impl Into<String>) -> Self {
+        Self { path: path.into_iter().collect(), line: None, message: message.into() }
+    }
+
+    fn into_anyhow(self, file: &Path, text: &str) -> anyhow::Error {
+        let path = PathDisplay(&self.path);
+        // a fully resolved key path is more precise than the parser position,
+        // which points at the enclosing node for errors raised by custom impls
+        let line = match locate(text, &self.path) {
+            Some((line, true)) => Some(line),
+            partial => self.line.or(partial.map(|(line, _)| line)),
+        };
+        match line {
+            Some(line) => anyhow!("{}:{}: `{}`: {}", file.display(), line, path, self.message),
+            None => anyhow!("{}: `{}`: {}", file.display(), path, self.message),
+        }
+    }
+}
+
+struct PathDisplay<'a>(&'a [Seg]);
+
+impl fmt::Display for PathDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, seg) in self.0.iter().enumerate() {
+            match seg {
+                Seg::Key(k) if i == 0 => write!(f, "{}", k)?,
+                Seg::Key(k) => write!(f, ".{}", k)?,
+                Seg::Index(n) => write!(f, "[{}]", n)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Best-effort 1-based line of a key path: each key is searched for after the
+/// line where the previous one was found; list indices count the `- ` items
+/// of the block under that line. The flag tells whether every segment was found.
+fn locate(text: &str, path: &[Seg]) -> Option<(usize, bool)> {
+    let lines: Vec<&str> = text.lines().map(|l| l.split(" #").next().unwrap_or(l)).collect();
+    let mut found: Option<usize> = None;
+    for seg in path {
+        let hit = match seg {
+            Seg::Key(k) => (found.unwrap_or(0)..lines.len()).find(|&i| line_has_key(lines[i], k)),
+            Seg::Index(n) => nth_item(&lines, found, *n),
+        };
+        match hit {
+            Some(i) => found = Some(i),
+            None => return found.map(|i| (i + 1, false)),
+        }
+    }
+    found.map(|i| (i + 1, true))
+}
+
+/// Line of the `n`-th block-sequence item following `parent` (or the top level).
+fn nth_item(lines: &[&str], parent: Option<usize>, n: usize) -> Option<usize> {
+    let parent_indent = parent.map_or(0, |p| indent(lines[p]));
+    let mut item_indent = None;
+    let mut seen = 0;
+    for (i, line) in lines.iter().enumerate().skip(parent.map_or(0, |p| p + 1)) {
+        let t = line.trim_start();
+        if t.is_empty() || t.starts_with('#') {
+            continue;
+        }
+        let is_item = t.starts_with("- ") || t == "-";
+        if parent.is_some() && (indent(line) < parent_indent || (indent(line) == parent_indent && !is_item)) {
+            return None;
+        }
+        if is_item && *item_indent.get_or_insert(indent(line)) == indent(line) {
+            if seen == n {
+                return Some(i);
+            }
+            seen += 1;
+        }
+    }
+    None
+}
+
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+fn line_has_key(line: &str, key: &str) -> bool {
+    if line.trim_start().starts_with('#') {
+        return false;
+    }
+    let needles = [format!("{}:", key), format!("\"{}\":", key)];
+    needles.iter().any(|needle| {
+        line.match_indices(needle.as_str()).any(|(pos, _)| {
+            line[..pos].chars().next_back().is_none_or(|c| c.is_whitespace() || "{,-".contains(c))
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RULES: &str = "\
+entities:
+  chip: { kind: token }
+zones:
+  pile: { shape: stack, visibility: all }
+verbs:
+  take:
+    params: { chip: Id }
+    pre:
+      - turnOf: { player: actor }
+    effect:
+      - move: { from: pile, to: bank, entity: $chip }
+phases:
+  - id: turn
+    activePlayer: sequential
+    verbs: [take]
+setup:
+  - initZone: { zone: pile, entity: chip, count: 3 }
+";
+
+    /// The message `Rules::load` would give for `text` read from `entities.yaml`.
+    fn error(text: &str) -> String {
+        let err = Rules::parse(text).expect_err("rules are rejected");
+        err.into_anyhow(Path::new("entities.yaml"), text).to_string()
+    }
+
+    #[test]
+    fn the_sample_rules_load() {
+        Rules::parse(RULES).unwrap();
+    }
+
+    #[test]
+    fn an_unknown_precondition_names_its_line_and_path() {
+        let text = RULES.replace("turnOf: { player: actor }", "onTurn: { player: actor }");
+        let message = error(&text);
+        assert!(
+            message.starts_with("entities.yaml:9: `verbs.take.pre[0].onTurn`: unknown precondition `onTurn` (known: turnOf, "),
+            "{}",
+            message
+        );
+    }
+
+    #[test]
+    fn an_unknown_field_names_its_line_and_path() {
+        let text = RULES.replace("  pile: { shape: stack, visibility: all }", "  pile:\n    shape: stack\n    facing: up");
+        assert_eq!(error(&text), "entities.yaml:6: `zones.pile.facing`: unknown field `facing`, expected one of \
+             `shape`, `visibility`, `mutable`, `perPlayer`, `width`, `height`");
+    }
+
+    #[test]
+    fn a_nested_setup_error_names_its_line_and_path() {
+        let text = RULES.replace(
+            "  - initZone: { zone: pile, entity: chip, count: 3 }",
+            "  - forEachPlayer:\n      - repeat:\n          times: 2\n          actions:\n            - shuffle: { zone: pile }\n            - deal",
+        );
+        assert_eq!(error(&text), "entities.yaml:22: `setup[0].forEachPlayer[0].repeat.actions[1]`: `deal` is neither a verb nor an effect");
+    }
+
+    #[test]
+    fn a_hook_can_only_be_limited_to_a_declared_phase_of_a_phase_event() {
+        let text = format!("{}hooks:\n  check: {{ on: on_phase_start, phase: resolve }}\n", RULES);
+        assert_eq!(error(&text), "entities.yaml:19: `hooks.check.phase`: unknown phase `resolve`");
+        let text = format!("{}hooks:\n  check: {{ on: on_after_play, phase: turn }}\n", RULES);
+        assert_eq!(
+            error(&text),
+            "entities.yaml:19: `hooks.check.phase`: only `on_phase_start` and `on_phase_end` hooks can be limited to a phase"
+        );
+        let text = format!("{}hooks:\n  check: {{ on: on_phase_start, phase: turn }}\n", RULES);
+        assert_eq!(Rules::parse(&text).unwrap().hooks["check"].phase.as_deref(), Some("turn"));
+    }
+}
@@ -0,0 +1,602 @@
+//! hooks.rs – wasmtime runtime for a bundle's `script.wasm`
+//! Compiled once per bundle, instantiated once per match. Hooks are exported
+//! `fn(ptr: i32, len: i32)` taking a JSON payload; they talk back through the
+//! `host` import module of the Rust SDK (`sdk/rust`).
+//! Every tick's hooks share one budget from the server's `HookLimits`: fuel and
+//! a wall-clock deadline enforced through epoch interruption; each instance's
+//! linear memory is capped.
+
+use crate::engine::Rng;
+use crate::entities::HookSubscription;
+use anyhow::{anyhow, bail, Context};
+use indexmap::IndexMap;
+use serde_json::Value;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+use wasmtime::{
+    AsContextMut, Caller, Engine, ExternType, Instance, Linker, Memory, Module, Mutability, Store, StoreLimits,
+    StoreLimitsBuilder, Trap, Val, ValType,
+};
+
+const PAGE: u64 = 64 * 1024;
+
+/// How often the engine's epoch advances; the timeout is counted in these.
+const EPOCH_TICK: Duration = Duration::from_millis(10);
+
+/// One wasmtime engine for the whole server; modules compiled on it can be
+/// instantiated in any store. Creating it starts the epoch ticker thread.
+fn engine() -> &'static Engine {
+    static ENGINE: OnceLock<Engine> = OnceLock::new();
+    ENGINE.get_or_init(|| {
+        let mut config = wasmtime::Config::new();
+        // rustc targets wasm32 with reference types on by default
+        config.wasm_reference_types(true);
+        config.consume_fuel(true);
+        config.epoch_interruption(true);
+        let engine = Engine::new(&config).expect("wasmtime engine config is valid");
+        let ticker = engine.clone();
+        std::thread::spawn(move || loop {
+            std::thread::sleep(EPOCH_TICK);
+            ticker.increment_epoch();
+        });
+        engine
+    })
+}
+
+/* --------------------------------------------------------------------------
+   limits
+   ----------------------------------------------------------------------- */
+
+/// What happens to a tick whose hook traps, runs out of fuel or times out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailurePolicy {
+    /// roll the whole verb back and tell every player (default)
+    Abort,
+    /// drop that hook's output and carry on with the verb
+    Skip,
+}
+
+/// Per-server budget for the hooks of one tick, however many of them it runs.
+#[derive(Debug, Clone, Copy)]
+pub struct HookLimits {
+    /// `BLUEFELT_HOOK_FUEL`: wasm fuel units, roughly one per instruction
+    pub fuel: u64,
+    /// `BLUEFELT_HOOK_MEMORY_MB`: largest linear memory a match's instance may grow to
+    pub memory_bytes: usize,
+    /// `BLUEFELT_HOOK_TIMEOUT_MS`: wall-clock deadline, counted from the start of the tick
+    pub timeout: Duration,
+    /// `BLUEFELT_HOOK_FAILURE`: `abort` or `skip`
+    pub on_failure: FailurePolicy,
+}
+
+impl HookLimits {
+    fn from_env() -> Self {
+        fn var(name: &str, default: u64) -> u64 {
+            std::env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+        }
+        Self {
+            fuel: var("BLUEFELT_HOOK_FUEL", 10_000_000),
+            memory_bytes: (var("BLUEFELT_HOOK_MEMORY_MB", 64) as usize) << 20,
+            timeout: Duration::from_millis(var("BLUEFELT_HOOK_TIMEOUT_MS", 250)),
+            on_failure: match std::env::var("BLUEFELT_HOOK_FAILURE").as_deref() {
+                Ok("skip") => FailurePolicy::Skip,
+                _ => FailurePolicy::Abort,
+            },
+        }
+    }
+}
+
+/// Epoch ticks in `time`; at least one.
+fn epoch_ticks(time: Duration) -> u64 {
+    (time.as_millis().div_ceil(EPOCH_TICK.as_millis()) as u64).max(1)
+}
+
+/// The limits in force, read from the environment on first use.
+pub fn limits() -> &'static HookLimits {
+    static LIMITS: OnceLock<HookLimits> = OnceLock::new();
+    LIMITS.get_or_init(HookLimits::from_env)
+}
+
+/// What the hooks of one tick have left between them: fuel and time.
+#[derive(Debug, Clone, Copy)]
+pub struct Budget {
+    fuel: u64,
+    deadline: Instant,
+}
+
+impl Budget {
+    /// The whole of `limits()`, with the deadline counted from now.
+    pub fn start() -> Self {
+        let limits = limits();
+        Self { fuel: limits.fuel, deadline: Instant::now() + limits.timeout }
+    }
+}
+
+/* --------------------------------------------------------------------------
+   compiled script (per bundle)
+   ----------------------------------------------------------------------- */
+
+#[derive(Clone)]
+pub struct Script {
+    module: Module,
+    linker: Arc<Linker<HostState>>,
+}
+
+impl Script {
+    /// Compile `script.wasm` and check that every hook in the registry is exported
+    /// with the `(i32, i32) -> ()` signature.
+    pub fn load(path: &Path, hooks: &IndexMap<String, HookSubscription>) -> anyhow::Result<Self> {
+        let module = Module::from_file(engine(), path).with_context(|| format!("cannot compile {}", path.display()))?;
+        for name in hooks.keys() {
+            let export = module.get_export(name);
+            let ok = match &export {
+                Some(ExternType::Func(f)) => {
+                    f.params().map(|p| matches!(p, ValType::I32)).eq([true, true]) && f.results().len() == 0
+                }
+                _ => false,
+            };
+            match (export, ok) {
+                (None, _) => bail!("{}: hook `{}` is not exported", path.display(), name),
+                (Some(_), false) => bail!("{}: hook `{}` must be `fn(i32, i32)`", path.display(), name),
+                _ => {}
+            }
+        }
+        if !matches!(module.get_export("memory"), Some(ExternType::Memory(_))) {
+            bail!("{}: module does not export `memory`", path.display());
+        }
+        Ok(Self { module, linker: Arc::new(host_linker()?) })
+    }
+
+    /// Fresh instance for one match.
+    pub fn instantiate(&self) -> anyhow::Result<HookRuntime> {
+        let limits = limits();
+        let host = HostState {
+            limits: StoreLimitsBuilder::new().memory_size(limits.memory_bytes).trap_on_grow_failure(true).build(),
+            ..HostState::default()
+        };
+        let mut store = Store::new(engine(), host);
+        store.limiter(|data| &mut data.limits);
+        // a start function runs under the same budget as a hook
+        store.set_fuel(limits.fuel)?;
+        store.set_epoch_deadline(epoch_ticks(limits.timeout));
+        let instance = self.linker.instantiate(&mut store, &self.module).context("cannot instantiate script.wasm")?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| anyhow!("module does not export `memory`"))?;
+        store.data_mut().memory = Some(memory);
+        Ok(HookRuntime { store, instance })
+    }
+
+    /// A fresh instance put back in the state `snapshot` was taken in.
+    pub fn restore(&self, snapshot: &Snapshot) -> anyhow::Result<HookRuntime> {
+        let mut runtime = self.instantiate()?;
+        let store = &mut runtime.store;
+        let memory = store.data().memory.ok_or_else(|| anyhow!("guest memory is not available"))?;
+        let missing = (snapshot.memory.len() as u64).saturating_sub(memory.data_size(&*store) as u64);
+        if missing > 0 {
+            memory.grow(&mut *store, missing / PAGE).context("cannot restore guest memory")?;
+        }
+        memory.write(&mut *store, 0, &snapshot.memory)?;
+        for (name, value) in &snapshot.globals {
+            let global = runtime
+                .instance
+                .get_global(&mut *store, name)
+                .ok_or_else(|| anyhow!("global `{}` is not exported", name))?;
+            global.set(&mut *store, value.clone())?;
+        }
+        store.data_mut().scratch = snapshot.scratch;
+        Ok(runtime)
+    }
+}
+
+/* --------------------------------------------------------------------------
+   instance (per match)
+   ----------------------------------------------------------------------- */
+
+pub struct HookRuntime {
+    store: Store<HostState>,
+    instance: Instance,
+}
+
+/// An instance between two calls: its linear memory, its exported mutable globals
+/// and the host's scratch region. Globals it doesn't export are taken to be back
+/// at their initial values between calls, as the SDK's stack pointer is.
+pub struct Snapshot {
+    memory: Vec<u8>,
+    globals: Vec<(String, Val)>,
+    scratch: Scratch,
+}
+
+/// What one hook call asked for.
+#[derive(Debug, Default)]
+pub struct HookOutcome {
+    /// JSON-Patch ops passed to `emit`, already checked against the state
+    pub ops: Vec<Value>,
+    pub advance_turn: bool,
+    /// `round_end(winner)`; `"draw"` when nobody won
+    pub round_end: Option<String>,
+    /// `ask(request)`: `{ player, choice, prompt?, then?, context? }`
+    pub ask: Option<Value>,
+}
+
+impl HookRuntime {
+    /// Everything a later call could depend on, for `Script::restore`.
+    pub fn snapshot(&mut self) -> Snapshot {
+        let memory = self.store.data().memory.map(|m| m.data(&self.store).to_vec()).unwrap_or_default();
+        let exports: Vec<_> = self
+            .instance
+            .exports(&mut self.store)
+            .filter_map(|e| Some((e.name().to_string(), e.into_global()?)))
+            .collect();
+        let mut globals = Vec::new();
+        for (name, global) in exports {
+            if global.ty(&self.store).mutability() != Mutability::Var {
+                continue;
+            }
+            let value = global.get(&mut self.store);
+            if matches!(value, Val::I32(_) | Val::I64(_) | Val::F32(_) | Val::F64(_) | Val::V128(_)) {
+                globals.push((name, value));
+            }
+        }
+        Snapshot { memory, globals, scratch: self.store.data().scratch }
+    }
+
+    /// Value of an exported `i32` global, for tests that watch a script's state.
+    #[cfg(test)]
+    pub fn global_i32(&mut self, name: &str) -> Option<i32> {
+        self.instance.get_global(&mut self.store, name)?.get(&mut self.store).i32()
+    }
+
+    /// Run hook `name` against `state` with `payload`. The state is only read;
+    /// the caller applies the outcome. `random` draws from `rng`, which only
+    /// advances if the hook succeeds. The call spends from `budget`, and fails
+    /// without running once the budget's deadline has passed.
+    pub fn call(
+        &mut self,
+        name: &str,
+        payload: &Value,
+        state: &Value,
+        rng: &mut Rng,
+        budget: &mut Budget,
+    ) -> anyhow::Result<HookOutcome> {
+        let limits = limits();
+        let time_left = budget.deadline.saturating_duration_since(Instant::now());
+        if time_left.is_zero() {
+            bail!("hook `{}` not run: the tick's hooks used up their {:?}", name, limits.timeout);
+        }
+        {
+            let data = self.store.data_mut();
+            data.state = state.clone();
+            data.rng = rng.clone();
+            data.outcome = HookOutcome::default();
+            data.scratch.next = data.scratch.start;
+        }
+        let bytes = payload.to_string().into_bytes();
+        let ptr = write_scratch(&mut self.store, &bytes)?;
+        let hook = self
+            .instance
+            .get_typed_func::<(u32, u32), ()>(&mut self.store, name)
+            .with_context(|| format!("hook `{}`", name))?;
+        self.store.set_fuel(budget.fuel)?;
+        self.store.set_epoch_deadline(epoch_ticks(time_left));
+        let result = hook.call(&mut self.store, (ptr, bytes.len() as u32));
+        budget.fuel = self.store.get_fuel()?;
+        result.map_err(|e| match e.downcast_ref::<Trap>() {
+            Some(Trap::OutOfFuel) => {
+                anyhow!("hook `{}` ran out of fuel (the tick's hooks have {} units)", name, limits.fuel)
+            }
+            Some(Trap::Interrupt) => {
+                anyhow!("hook `{}` timed out (the tick's hooks have {:?})", name, limits.timeout)
+            }
+            _ => e.context(format!("hook `{}` trapped", name)),
+        })?;
+        let data = self.store.data_mut();
+        data.state = Value::Null;
+        *rng = std::mem::take(&mut data.rng);
+        Ok(std::mem::take(&mut data.outcome))
+    }
+}
+
+/* --------------------------------------------------------------------------
+   host imports
+   ----------------------------------------------------------------------- */
+
+#[derive(Default)]
+pub struct HostState {
+    /// match state for the duration of one call; `emit` applies to it so later
+    /// reads in the same hook see the change
+    state: Value,
+    outcome: HookOutcome,
+    memory: Option<Memory>,
+    scratch: Scratch,
+    limits: StoreLimits,
+    /// the match's RNG for the duration of one call
+    rng: Rng,
+}
+
+/// Host-owned region of guest memory for values handed back to the guest
+/// (`owner_of`, `get_grid`, payloads). SDK modules export no allocator, so the
+/// host grows memory itself and bump-allocates, resetting at every call.
+#[derive(Debug, Default, Clone, Copy)]
+struct Scratch {
+    start: u64,
+    next: u64,
+    end: u64,
+}
+
+fn host_linker() -> anyhow::Result<Linker<HostState>> {
+    let mut linker = Linker::new(engine());
+
+    linker.func_wrap("host", "emit", |mut caller: Caller<'_, HostState>, ptr: u32, len: u32| -> anyhow::Result<()> {
+        let text = read_string(&mut caller, ptr, len)?;
+        let value: Value = serde_json::from_str(&text).with_context(|| format!("emit: invalid JSON {:?}", text))?;
+        let ops = match value {
+            Value::Array(ops) => ops,
+            op => vec![op],
+        };
+        let data = caller.data_mut();
+        for op in ops {
+            crate::engine::apply_patch_op(&mut data.state, &op).context("emit")?;
+            data.outcome.ops.push(op);
+        }
+        Ok(())
+    })?;
+
+    linker.func_wrap("host", "zone_len", |mut caller: Caller<'_, HostState>, ptr: u32, len: u32| -> anyhow::Result<u32> {
+        let zone = read_string(&mut caller, ptr, len)?;
+        Ok(zone_len(&caller.data().state, &zone))
+    })?;
+
+    linker.func_wrap("host", "owner_of", |mut caller: Caller<'_, HostState>, ptr: u32, len: u32| -> anyhow::Result<u32> {
+        let id = read_string(&mut caller, ptr, len)?;
+        match owner_of(&caller.data().state, &id) {
+            Some(owner) => {
+                let mut bytes = owner.into_bytes();
+                bytes.push(0);
+                write_scratch(&mut caller, &bytes)
+            }
+            None => Ok(0),
+        }
+    })?;
+
+    linker.func_wrap(
+        "host",
+        "get_grid",
+        |mut caller: Caller<'_, HostState>, ptr: u32, len: u32, out_ptr: u32| -> anyhow::Result<u32> {
+            let zone = read_string(&mut caller, ptr, len)?;
+            let grid = caller.data().state["zones"].get(&zone).filter(|z| z.is_array()).cloned();
+            let Some(grid) = grid else { return Ok(0) };
+            let bytes = grid.to_string().into_bytes();
+            let data_ptr = write_scratch(&mut caller, &bytes)?;
+            memory(&caller)?.write(&mut caller, out_ptr as usize, &data_ptr.to_le_bytes())?;
+            Ok(bytes.len() as u32)
+        },
+    )?;
+
+    linker.func_wrap("host", "random", |mut caller: Caller<'_, HostState>, bound: u32| -> u32 {
+        caller.data_mut().rng.below(bound as u64) as u32
+    })?;
+
+    linker.func_wrap("host", "advance_turn", |mut caller: Caller<'_, HostState>| {
+        caller.data_mut().outcome.advance_turn = true;
+    })?;
+
+    linker.func_wrap("host", "round_end", |mut caller: Caller<'_, HostState>, ptr: u32, len: u32| -> anyhow::Result<()> {
+        let winner = read_string(&mut caller, ptr, len)?;
+        caller.data_mut().outcome.round_end = Some(winner);
+        Ok(())
+    })?;
+
+    linker.func_wrap("host", "ask", |mut caller: Caller<'_, HostState>, ptr: u32, len: u32| -> anyhow::Result<()> {
+        let text = read_string(&mut caller, ptr, len)?;
+        let request = serde_json::from_str(&text).with_context(|| format!("ask: invalid JSON {:?}", text))?;
+        let outcome = &mut caller.data_mut().outcome;
+        if outcome.ask.is_some() {
+            bail!("ask: a hook can only ask one question");
+        }
+        outcome.ask = Some(request);
+        Ok(())
+    })?;
+
+    Ok(linker)
+}
+
+fn memory(caller: &Caller<'_, HostState>) -> anyhow::Result<Memory> {
+    caller.data().memory.ok_or_else(|| anyhow!("guest memory is not available"))
+}
+
+fn read_string(caller: &mut Caller<'_, HostState>, ptr: u32, len: u32) -> anyhow::Result<String> {
+    let mut buf = vec![0u8; len as usize];
+    memory(caller)?.read(&*caller, ptr as usize, &mut buf).context("read outside guest memory")?;
+    String::from_utf8(buf).context("guest string is not UTF-8")
+}
+
+/// Copy `bytes` into the scratch region and return their guest address.
+fn write_scratch(mut ctx: impl AsContextMut<Data = HostState>, bytes: &[u8]) -> anyhow::Result<u32> {
+    let mut store = ctx.as_context_mut();
+    let memory = store.data().memory.ok_or_else(|| anyhow!("guest memory is not available"))?;
+    let needed = bytes.len() as u64;
+    let Scratch { next, end, .. } = store.data().scratch;
+    if next + needed > end {
+        // fresh pages at the end of memory; the guest allocator never hands them out
+        let pages = needed.div_ceil(PAGE).max(1);
+        let old = memory.grow(&mut store, pages).context("hook memory limit reached")?;
+        let scratch = &mut store.data_mut().scratch;
+        scratch.start = old * PAGE;
+        scratch.next = scratch.start;
+        scratch.end = (old + pages) * PAGE;
+    }
+    let at = store.data().scratch.next;
+    memory.write(&mut store, at as usize, bytes)?;
+    // keep the next value 8-byte aligned
+    store.data_mut().scratch.next = (at + needed + 7) & !7;
+    u32::try_from(at).map_err(|_| anyhow!("scratch address beyond 4 GiB"))
+}
+
+/* --------------------------------------------------------------------------
+   state queries
+   ----------------------------------------------------------------------- */
+
+/// Entities in a zone: list length, occupied cells of a grid, 1/0 for a flag.
+/// `zone/p1` addresses one slot of a per-player zone; the bare name sums all slots.
+fn zone_len(state: &Value, zone: &str) -> u32 {
+    fn count(v: &Value) -> u32 {
+        match v {
+            Value::Array(items) => items.iter().map(|i| if i.is_array() { count(i) } else { !i.is_null() as u32 }).sum(),
+            Value::Object(slots) => slots.values().map(count).sum(),
+            Value::Bool(b) => *b as u32,
+            _ => 0,
+        }
+    }
+    state.pointer(&format!("/zones/{}", zone)).map_or(0, count)
+}
+
+/// Seat owning an entity: its `owner` field, else the player whose `mark`
+/// is its template, else the per-player zone slot it sits in.
+fn owner_of(state: &Value, id: &str) -> Option<String> {
+    let entity = state["entities"].get(id)?;
+    if let Some(owner) = entity["owner"].as_str() {
+        return Some(owner.to_string());
+    }
+    let players = state["players"].as_array()?;
+    if let Some(p) = players.iter().find(|p| p["mark"].is_string() && p["mark"] == entity["template"]) {
+        return p["id"].as_str().map(str::to_string);
+    }
+    state["zones"].as_object()?.values().filter_map(Value::as_object).find_map(|slots| {
+        slots
+            .iter()
+            .find(|(_, pile)| pile.as_array().is_some_and(|items| items.iter().any(|i| i == id)))
+            .map(|(seat, _)| seat.clone())
+    })
+}
+
+/* --------------------------------------------------------------------------
+   hand-assembled scripts for tests
+   ----------------------------------------------------------------------- */
+
+/// `script.wasm` modules built byte by byte, since the `wat` text format is
+/// not compiled in. A module imports `emit`, `advance_turn` and `round_end`,
+/// exports one page of `memory` starting with `data`, a mutable `i32` global
+/// `count`, and one `fn(i32, i32)` hook per `(name, body)`. A body is raw
+/// instructions without the trailing `end`.
+#[cfg(test)]
+pub(crate) mod test_script {
+    pub const EMIT: u8 = 0;
+    pub const ADVANCE_TURN: u8 = 1;
+    pub const ROUND_END: u8 = 2;
+
+    /// `count += 1`
+    pub const COUNT: &[u8] = &[0x23, 0x00, 0x41, 0x01, 0x6a, 0x24, 0x00];
+
+    /// `unreachable`
+    pub const TRAP: &[u8] = &[0x00];
+
+    /// `loop { br 0 }`
+    pub const LOOP_FOREVER: &[u8] = &[0x03, 0x40, 0x0c, 0x00, 0x0b];
+
+    /// Fuel one round of `spin` burns: eight instructions at one unit each.
+    pub const SPIN_FUEL: u64 = 8;
+
+    /// `count = 0`, then `count += 1` until it reaches `rounds`.
+    pub fn spin(rounds: i32) -> Vec<u8> {
+        let mut code = vec![0x41, 0x00, 0x24, 0x00, 0x03, 0x40];
+        code.extend(COUNT);
+        code.extend([0x23, 0x00, 0x41]);
+        code.extend(sleb(rounds as i64));
+        code.extend([0x48, 0x0d, 0x00, 0x0b]);
+        code
+    }
+
+    /// Call host import `import` with `(ptr, len)` of `data[offset..offset + len]`, or no args.
+    pub fn call(import: u8, args: Option<(usize, usize)>) -> Vec<u8> {
+        let mut code = Vec::new();
+        if let Some((offset, len)) = args {
+            for n in [offset, len] {
+                code.push(0x41);
+                code.extend(sleb(n as i64));
+            }
+        }
+        code.extend([0x10, import]);
+        code
+    }
+
+    pub fn module(hooks: &[(&str, Vec<u8>)], data: &[u8]) -> Vec<u8> {
+        let mut wasm = b"\0asm\x01\0\0\0".to_vec();
+        // types: 0 = (i32, i32) -> (), 1 = () -> ()
+        section(&mut wasm, 1, &[0x02, 0x60, 0x02, 0x7f, 0x7f, 0x00, 0x60, 0x00, 0x00]);
+        let mut imports = vec![0x03];
+        for (name, ty) in [("emit", 0), ("advance_turn", 1), ("round_end", 0)] {
+            imports.extend(string("host"));
+            imports.extend(string(name));
+            imports.extend([0x00, ty]);
+        }
+        section(&mut wasm, 2, &imports);
+        let mut functions = leb(hooks.len());
+        functions.extend(std::iter::repeat_n(0x00, hooks.len()));
+        section(&mut wasm, 3, &functions);
+        section(&mut wasm, 5, &[0x01, 0x00, 0x01]);
+        section(&mut wasm, 6, &[0x01, 0x7f, 0x01, 0x41, 0x00, 0x0b]);
+        let mut exports = leb(hooks.len() + 2);
+        exports.extend(string("memory"));
+        exports.extend([0x02, 0x00]);
+        exports.extend(string("count"));
+        exports.extend([0x03, 0x00]);
+        for (i, (name, _)) in hooks.iter().enumerate() {
+            exports.extend(string(name));
+            exports.push(0x00);
+            exports.extend(leb(3 + i));
+        }
+        section(&mut wasm, 7, &exports);
+        let mut code = leb(hooks.len());
+        for (_, body) in hooks {
+            let body = [&[0x00][..], body, &[0x0b]].concat();
+            code.extend(leb(body.len()));
+            code.extend(body);
+        }
+        section(&mut wasm, 10, &code);
+        let mut segment = vec![0x01, 0x00, 0x41, 0x00, 0x0b];
+        segment.extend(leb(data.len()));
+        segment.extend(data);
+        section(&mut wasm, 11, &segment);
+        wasm
+    }
+
+    fn section(wasm: &mut Vec<u8>, id: u8, contents: &[u8]) {
+        wasm.push(id);
+        wasm.extend(leb(contents.len()));
+        wasm.extend(contents);
+    }
+
+    fn string(s: &str) -> Vec<u8> {
+        let mut bytes = leb(s.len());
+        bytes.extend(s.as_bytes());
+        bytes
+    }
+
+    fn leb(mut n: usize) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        loop {
+            let byte = (n & 0x7f) as u8;
+            n >>= 7;
+            if n == 0 {
+                bytes.push(byte);
+                return bytes;
+            }
+            bytes.push(byte | 0x80);
+        }
+    }
+
+    fn sleb(mut n: i64) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        loop {
+            let byte = (n & 0x7f) as u8;
+            n >>= 7;
+            if (n == 0 && byte & 0x40 == 0) || (n == -1 && byte & 0x40 != 0) {
+                bytes.push(byte);
+                return bytes;
+            }
+            bytes.push(byte | 0x80);
+        }
+    }
+}
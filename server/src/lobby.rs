@@ -1,21 +1,55 @@
 //! lobby.rs – minimal in-memory lobby with broadcast fan-out
-//! Supports: welcome snapshot → JSON verb → diff broadcast
+//! Supports: welcome snapshot → JSON verb → diff fan-out, each seat seeing
+//! only what zone visibility allows
 
 use crate::{bundle::Bundle, engine};
 use axum::extract::ws::{Message, WebSocket};
 use dashmap::DashMap;
 use futures_util::{SinkExt, StreamExt};
 use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use tokio::sync::{broadcast, Mutex as TokioMutex};
-use chrono;
+use tokio::sync::{broadcast, broadcast::error::RecvError, Mutex as TokioMutex};
 
 pub type LobbyMap = DashMap<String, Arc<Lobby>>;
 
+/// One server message in each recipient's version: seated players get their
+/// own projection, any other socket the `public` one.
+#[derive(Debug)]
+pub struct Outbound {
+    by_seat: HashMap<String, String>,
+    public: String,
+    /// `seq` of the event it carries, 0 for anything else
+    seq: u64,
+}
+
+impl Outbound {
+    /// The same text for every recipient.
+    fn everyone(text: String) -> Arc<Self> {
+        Arc::new(Self { by_seat: HashMap::new(), public: text, seq: 0 })
+    }
+
+    fn for_seat(&self, seat: &str) -> Message {
+        Message::Text(self.by_seat.get(seat).unwrap_or(&self.public).clone())
+    }
+}
+
+/// What `seat` may play now (see engine/legal.rs), with the `tick`/`seq` it applies to.
+fn legal_moves_message(game: &engine::Match, seat: &str) -> String {
+    serde_json::json!({
+        "type": "legalMoves",
+        "tick": game.tick(),
+        "seq": game.seq(),
+        "verbs": game.legal_moves(seat)
+    })
+    .to_string()
+}
+
 /* --------------------------------------------------------------------------
    constructor helper
    ----------------------------------------------------------------------- */
-pub fn new_lobby(id: String, bundle: Bundle) -> Arc<Lobby> {
+pub fn new_lobby(id: String, bundle: Arc<Bundle>) -> Arc<Lobby> {
     Arc::new(Lobby::new(id, bundle))
 }
 
@@ -24,32 +58,36 @@ pub fn new_lobby(id: String, bundle: Bundle) -> Arc<Lobby> {
    ----------------------------------------------------------------------- */
 pub struct Lobby {
     pub id: String,
-    pub bundle: Bundle,
+    pub bundle: Arc<Bundle>,
 
-    /// authoritative mutable state (JSON object)
-    state: Mutex<serde_json::Value>,
+    /// authoritative game, created once enough players have joined; hooks run
+    /// wasm, so it is only set up and played on the blocking pool
+    game: Arc<TokioMutex<Option<engine::Match>>>,
 
-    /// broadcast channel for diff events
-    tx: broadcast::Sender<Message>,
+    /// fan-out channel for events; every socket picks its seat's version
+    tx: broadcast::Sender<Arc<Outbound>>,
     
     /// Track connected players
     players: Mutex<Vec<String>>,
     
     /// Game has started flag
     game_started: Mutex<bool>,
+
+    /// why the last attempt to start the match failed, told to anyone who connects
+    setup_error: Mutex<Option<String>>,
 }
 
 impl Lobby {
-    pub fn new(id: String, bundle: Bundle) -> Self {
-        let initial = engine::load_initial_state(&bundle);
+    pub fn new(id: String, bundle: Arc<Bundle>) -> Self {
         let (tx, _) = broadcast::channel(64);
         Self {
             id,
             bundle,
-            state: Mutex::new(initial),
+            game: Arc::new(TokioMutex::new(None)),
             tx,
             players: Mutex::new(Vec::new()),
             game_started: Mutex::new(false),
+            setup_error: Mutex::new(None),
         }
     }
 
@@ -65,32 +103,142 @@ impl Lobby {
         players.clone()
     }
 
-    pub fn add_player(&self, player_id: String) -> bool {
-        let mut players = self.players.lock();
-        
-        // If this is the same player reconnecting, allow it
-        if players.contains(&player_id) {
-            println!("[Socket] Player {} is reconnecting to the lobby", player_id);
-            return true;
-        }
-        
-        // Check if we already have 2 players (max for tic-tac-toe)
-        if players.len() < 2 {
+    pub async fn add_player(&self, player_id: String) -> bool {
+        let full_table = {
+            let mut players = self.players.lock();
+
+            // If this is the same player reconnecting, allow it
+            if players.contains(&player_id) {
+                println!("[Socket] Player {} is reconnecting to the lobby", player_id);
+                return true;
+            }
+
+            // Seats are fixed once the game is running
+            let capacity = self.bundle.manifest.metadata.players.max as usize;
+            if players.len() >= capacity || self.is_started() {
+                println!("Could not add player {} - lobby is full", player_id);
+                return false;
+            }
             println!("[Socket] Adding new player {} to the lobby", player_id);
             players.push(player_id);
-            // If we now have 2 players, start the game
-            if players.len() == 2 {
+            // a full table starts on its own; below that someone has to send `start`
+            (players.len() == capacity).then(|| players.clone())
+        };
+        if let Some(players) = full_table {
+            // a failure has already been reported to the lobby
+            let _ = self.start(players).await;
+        }
+        true
+    }
+
+    /// `{"type":"start"}` from `player_id`: start the match with whoever has
+    /// joined, as long as that is at least the bundle's minimum.
+    async fn request_start(&self, player_id: &str) -> Option<engine::Rejection> {
+        use engine::{RejectCode, Rejection};
+        let players = self.player_list();
+        let min = self.bundle.manifest.metadata.players.min as usize;
+        let refusal = if self.is_started() {
+            "the game has already started".to_string()
+        } else if !players.iter().any(|p| p == player_id) {
+            format!("{} has no seat in this lobby", player_id)
+        } else if players.len() < min {
+            format!("{} needs at least {} players, {} joined", self.bundle.game_id, min, players.len())
+        } else {
+            println!("[Socket] Player {} asked to start lobby {} with {} players", player_id, self.id, players.len());
+            return self.start(players).await.err().map(|e| Rejection::new(RejectCode::CannotStart, e));
+        };
+        println!("[Socket] Refused to start lobby {} for player {}: {}", self.id, player_id, refusal);
+        Some(Rejection::new(RejectCode::CannotStart, refusal))
+    }
+
+    /// Set up the match for `players`. When that fails every socket in the lobby
+    /// is told why, and so is anyone who connects later, and the lobby stays open.
+    async fn start(&self, players: Vec<String>) -> Result<(), String> {
+        let mut game = self.game.lock().await;
+        // a full table and a `start` can race here; the first one sets the game up
+        if game.is_some() {
+            return Err("the game has already started".to_string());
+        }
+        let seed = engine::fresh_seed();
+        let bundle = self.bundle.clone();
+        let seats = players.len();
+        // setup hooks are wasm, which must not run on the async workers
+        let setup = tokio::task::spawn_blocking(move || engine::Match::new(bundle, &players, seed))
+            .await
+            .map_err(anyhow::Error::from)
+            .and_then(|result| result);
+        match setup {
+            Ok(new_game) => {
+                println!("[Socket] Lobby {} plays {} with seed {:#018x}", self.id, self.bundle.game_id, seed);
+                *game = Some(new_game);
                 *self.game_started.lock() = true;
-                println!("[Socket] Two players joined, starting the game!");
+                *self.setup_error.lock() = None;
+                println!("[Socket] {} players joined, starting the game!", seats);
+                Ok(())
+            }
+            Err(e) => {
+                println!("[Socket] ERROR: Could not set up {}: {:#}", self.bundle.game_id, e);
+                let message = format!("Could not set up {}: {:#}", self.bundle.game_id, e);
+                *self.setup_error.lock() = Some(message.clone());
+                let error = serde_json::json!({ "type": "error", "message": message });
+                // nobody may be listening yet, which is fine: new sockets get `setup_error`
+                let _ = self.tx.send(Outbound::everyone(error.to_string()));
+                Err(message)
             }
-            return true;
         }
-        
-        println!("Could not add player {} - lobby is full", player_id);
-        false
+    }
+
+    /// Seat id (`p1`, `p2`, …) of a user, by join order.
+    pub fn seat_of(&self, player_id: &str) -> Option<String> {
+        self.players.lock().iter().position(|p| p == player_id).map(engine::seat_id)
+    }
+
+    /// Welcome message for `seat`: the state as that seat may see it, and the
+    /// `tick`/`seq` it is at, taken together so no event falls in between.
+    async fn welcome(&self, seat: &str) -> serde_json::Value {
+        let game = self.game.lock().await;
+        serde_json::json!({
+            "type": "welcome",
+            "bundleMeta": self.bundle.meta(),
+            "initialState": game.as_ref().map(|g| g.view(Some(seat))).unwrap_or_default(),
+            "tick": game.as_ref().map_or(0, |g| g.tick()),
+            "seq": game.as_ref().map_or(0, |g| g.seq()),
+            "you": seat
+        })
+    }
+
+    /// Messages that bring `seat` up to date after it missed events, and the
+    /// `seq` they bring it to: the events after `since` as that seat was sent
+    /// them, from the log, or a fresh snapshot when `since` is unknown or the
+    /// socket has no seat. `None` while no game is running.
+    async fn catch_up(&self, seat: &str, since: Option<u64>) -> Option<(Vec<String>, u64)> {
+        let game = self.game.lock().await;
+        let game = game.as_ref()?;
+        let seq = game.seq();
+        let mut messages = match since {
+            Some(since) if since <= seq && !seat.is_empty() => {
+                game.log.events[since as usize..].iter().flat_map(|e| e.envelopes(seat)).map(|e| e.to_string()).collect()
+            }
+            _ => vec![serde_json::json!({
+                "type": "snapshot",
+                "state": game.view(Some(seat)),
+                "tick": game.tick(),
+                "seq": seq,
+                "you": seat
+            })
+            .to_string()],
+        };
+        messages.push(legal_moves_message(game, seat));
+        Some((messages, seq))
+    }
+
+    /// The `legalMoves` message for `seat`; `None` while no game is running.
+    async fn legal_moves(&self, seat: &str) -> Option<String> {
+        self.game.lock().await.as_ref().map(|g| legal_moves_message(g, seat))
     }
 
     /// Optional method to remove a player - normally not needed as disconnections are handled implicitly
+    #[allow(dead_code)]
     pub fn remove_player(&self, player_id: &str) -> bool {
         let mut players = self.players.lock();
         let before_len = players.len();
@@ -105,20 +253,86 @@ impl Lobby {
         false
     }
 
+    /// The log of this lobby's match once it is over; hidden information is in
+    /// it, so it is not handed out while the match runs.
+    pub async fn finished_log(&self) -> Option<engine::MatchLog> {
+        self.game.lock().await.as_ref().filter(|g| g.is_over()).map(|g| g.log.clone())
+    }
+
     /// Check if the game has started
     pub fn is_started(&self) -> bool {
         *self.game_started.lock()
     }
 
+    /// Apply a client's `{verb, args}` for `seat` and fan out the resulting
+    /// event; the rejection to send back if it was not applied.
+    async fn play(&self, seat: &str, player_id: &str, json: &serde_json::Value, text: &str) -> Option<engine::Rejection> {
+        use engine::{RejectCode, Rejection};
+        if !json["verb"].is_string() {
+            println!("[Socket] ERROR: Received a message without a verb from player {}: {}", player_id, text);
+            return Some(Rejection::new(RejectCode::BadMessage, "expected `{\"verb\": ..., \"args\": {...}}`"));
+        }
+        println!("[Socket] Received {} command from player {}: {}", json["verb"], player_id, text);
+        // the lock is held until every message is queued, so events go out in `seq` order
+        let mut game = self.game.clone().lock_owned().await;
+        if game.is_none() {
+            println!("[Socket] ERROR: Received command from player {} but game hasn't started yet", player_id);
+            return Some(Rejection::new(RejectCode::NotStarted, "the game has not started yet"));
+        }
+        let (tx, seat, player, json) = (self.tx.clone(), seat.to_string(), player_id.to_string(), json.clone());
+        // hooks are wasm and may run up to their time limit, so the verb is
+        // applied on the blocking pool rather than on an async worker
+        let applied = tokio::task::spawn_blocking(move || {
+            let game = game.as_mut()?;
+            match game.apply_verb(&seat, &json) {
+                Ok(tick) => {
+                    let event = Outbound {
+                        by_seat: tick.views.keys().map(|s| (s.clone(), tick.envelope(Some(s)).to_string())).collect(),
+                        public: tick.envelope(None).to_string(),
+                        seq: tick.seq,
+                    };
+                    // how the round ended, if it did, is the same news for everyone
+                    let round_end = tick.round_envelopes().into_iter().map(|e| Outbound::everyone(e.to_string()));
+                    // everyone's options change with every event
+                    let legal_moves = Outbound {
+                        by_seat: game.seats().into_iter().map(|s| (s.clone(), legal_moves_message(game, &s))).collect(),
+                        public: legal_moves_message(game, ""),
+                        seq: 0,
+                    };
+                    for outbound in std::iter::once(Arc::new(event)).chain(round_end).chain([Arc::new(legal_moves)]) {
+                        if let Err(e) = tx.send(outbound) {
+                            println!("[Socket] ERROR: Error broadcasting event: {}", e);
+                        }
+                    }
+                    None
+                }
+                Err(rejection) => {
+                    println!("[Socket] Rejected {} from player {}: {}", json["verb"], player, rejection);
+                    Some(rejection)
+                }
+            }
+        })
+        .await;
+        applied.unwrap_or_else(|e| {
+            println!("[Socket] ERROR: Applying a verb from player {} failed: {}", player_id, e);
+            Some(Rejection::new(RejectCode::EffectFailed, "the server failed to apply the verb"))
+        })
+    }
+
     /// Accept a new WebSocket client, drive send/recv loops.
-    pub async fn accept_client(self: Arc<Self>, socket: WebSocket) {
+    pub async fn accept_client(self: Arc<Self>, socket: WebSocket, player_id: String) {
         // --- split socket ---------------------------------------------------
         let (sink_raw, mut stream) = socket.split();
         let sink = Arc::new(TokioMutex::new(sink_raw)); // make clonable
         
         // --- 1️⃣ send welcome message regardless of game state ------------------------------------
         let is_game_started = *self.game_started.lock();
-        let player_id = self.players.lock().last().cloned().unwrap_or_else(|| "unknown".to_string());
+        let seat = self.seat_of(&player_id).unwrap_or_default();
+
+        // subscribe before the welcome so no event falls in between; `last_seq`
+        // is the latest event this socket has, and anything older is not sent again
+        let mut rx = self.tx.subscribe();
+        let last_seq = Arc::new(AtomicU64::new(0));
         
         println!("[Socket] WebSocket client connected for player: {}", player_id);
         
@@ -128,28 +342,8 @@ impl Lobby {
             
             if is_game_started {
                 // Game has started, send the full game state
-                let snapshot = {
-                    let guard = self.state.lock();
-                    guard.clone()
-                };
-                let welcome = serde_json::json!({
-                    "type": "welcome",
-                    "bundleMeta": {
-                        "cards": {},
-                        "verbs": {
-                            "place": {
-                                "ui": {
-                                    "prompt": "Place your mark",
-                                    "paramPrompts": {
-                                        "row": "Row (0-2)",
-                                        "col": "Column (0-2)"
-                                    }
-                                }
-                            }
-                        }
-                    },
-                    "initialState": snapshot
-                });
+                let welcome = self.welcome(&seat).await;
+                last_seq.store(welcome["seq"].as_u64().unwrap_or_default(), Ordering::SeqCst);
                 
                 println!("[Socket] Sending welcome message to player: {}", player_id);
                 if let Err(e) = locked.send(Message::Text(welcome.to_string())).await {
@@ -157,30 +351,28 @@ impl Lobby {
                     return;
                 }
                 
-                // Send legal moves for the initial game state
-                let legal_moves = serde_json::json!({
-                    "type": "legalMoves",
-                    "verbs": [
-                        {
-                            "verb": "place",
-                            "params": {
-                                "row": "u8",
-                                "col": "u8"
-                            }
-                        }
-                    ]
-                });
+                // Send legal moves for the current game state
+                let legal_moves = self.legal_moves(&seat).await.unwrap_or_default();
                 println!("[Socket] Sending legal moves to player: {}", player_id);
-                if let Err(e) = locked.send(Message::Text(legal_moves.to_string())).await {
+                if let Err(e) = locked.send(Message::Text(legal_moves)).await {
                     println!("[Socket] ERROR: Error sending legal moves: {}", e);
                     return;
                 }
             } else {
-                // Game not started yet, send waiting message
-                let waiting_msg = serde_json::json!({
-                    "type": "info",
-                    "message": "Waiting for another player to join..."
-                });
+                // Game not started yet: say why, or who is still missing
+                let waiting_msg = match self.setup_error.lock().clone() {
+                    Some(message) => serde_json::json!({ "type": "error", "message": message }),
+                    None => {
+                        let players = self.bundle.manifest.metadata.players;
+                        serde_json::json!({
+                            "type": "info",
+                            "message": format!(
+                                "Waiting for players: {} joined, {} to {} can play. Send {{\"type\":\"start\"}} to begin.",
+                                self.players(), players.min, players.max
+                            )
+                        })
+                    }
+                };
                 println!("[Socket] Sending waiting message to player: {}", player_id);
                 if let Err(e) = locked.send(Message::Text(waiting_msg.to_string())).await {
                     println!("[Socket] ERROR: Error sending waiting message: {}", e);
@@ -192,9 +384,10 @@ impl Lobby {
         /* spawn task to forward broadcast events */
         let forward_handle;
         {
-            let mut rx = self.tx.subscribe();
+            let last_seq = last_seq.clone();
             let sink_clone = sink.clone();
             let player_id_clone = player_id.clone();
+            let seat_clone = seat.clone();
             let self_clone = self.clone();
             
             // Set up forward task
@@ -207,28 +400,8 @@ impl Lobby {
                     let curr_game_started = *self_clone.game_started.lock();
                     if !last_game_started && curr_game_started {
                         // Game just started, send welcome message with game state
-                        let snapshot = {
-                            let guard = self_clone.state.lock();
-                            guard.clone()
-                        };
-                        let welcome = serde_json::json!({
-                            "type": "welcome",
-                            "bundleMeta": {
-                                "cards": {},
-                                "verbs": {
-                                    "place": {
-                                        "ui": {
-                                            "prompt": "Place your mark",
-                                            "paramPrompts": {
-                                                "row": "Row (0-2)",
-                                                "col": "Column (0-2)"
-                                            }
-                                        }
-                                    }
-                                }
-                            },
-                            "initialState": snapshot
-                        });
+                        let welcome = self_clone.welcome(&seat_clone).await;
+                        last_seq.store(welcome["seq"].as_u64().unwrap_or_default(), Ordering::SeqCst);
                         
                         // Use a different approach to avoid borrow checker issues
                         let sink_for_welcome = sink_clone.clone();
@@ -246,20 +419,9 @@ impl Lobby {
                                 }
                                 
                                 // Send legal moves
-                                let legal_moves = serde_json::json!({
-                                    "type": "legalMoves",
-                                    "verbs": [
-                                        {
-                                            "verb": "place",
-                                            "params": {
-                                                "row": "u8",
-                                                "col": "u8"
-                                            }
-                                        }
-                                    ]
-                                });
+                                let legal_moves = self_clone.legal_moves(&seat_clone).await.unwrap_or_default();
                                 println!("[Socket] Sending legal moves to player: {}", player_id_clone);
-                                if let Err(e) = locked.send(Message::Text(legal_moves.to_string())).await {
+                                if let Err(e) = locked.send(Message::Text(legal_moves)).await {
                                     println!("[Socket] ERROR: Error sending legal moves on game start: {}", e);
                                     return;
                                 }
@@ -275,7 +437,7 @@ impl Lobby {
                     
                     // Wait for messages or timeout
                     tokio::select! {
-                        Ok(msg) = rx.recv() => {
+                        received = rx.recv() => {
                             // Use a different approach to avoid borrow checker issues
                             let sink_for_broadcast = sink_clone.clone();
                             
@@ -288,9 +450,26 @@ impl Lobby {
                             // Handle the result
                             match lock_attempt {
                                 Ok(mut locked) => {
-                                    if let Err(e) = locked.send(msg).await {
-                                        println!("[Socket] ERROR: Error forwarding message to client: {}", e);
-                                        return;
+                                    let messages = match received {
+                                        Ok(msg) if msg.seq != 0 && msg.seq <= last_seq.load(Ordering::SeqCst) => continue,
+                                        Ok(msg) => {
+                                            last_seq.fetch_max(msg.seq, Ordering::SeqCst);
+                                            vec![msg.for_seat(&seat_clone)]
+                                        }
+                                        // the channel dropped events for this socket: send what it missed
+                                        Err(RecvError::Lagged(skipped)) => {
+                                            println!("[Socket] WARNING: Player {} fell {} messages behind, resyncing", player_id_clone, skipped);
+                                            let Some((messages, seq)) = self_clone.catch_up(&seat_clone, Some(last_seq.load(Ordering::SeqCst))).await else { continue };
+                                            last_seq.fetch_max(seq, Ordering::SeqCst);
+                                            messages.into_iter().map(Message::Text).collect()
+                                        }
+                                        Err(RecvError::Closed) => return,
+                                    };
+                                    for message in messages {
+                                        if let Err(e) = locked.send(message).await {
+                                            println!("[Socket] ERROR: Error forwarding message to client: {}", e);
+                                            return;
+                                        }
                                     }
                                 },
                                 Err(_) => {
@@ -383,43 +562,51 @@ impl Lobby {
             match result {
                 Ok(Message::Text(text)) => {
                     // First try to parse the JSON
-                    match serde_json::from_str::<serde_json::Value>(&text) {
-                        Ok(json) => {
-                            // Process the verb only if game has started
-                            if *self.game_started.lock() {
-                                // Handle the json command
-                                if json["verb"] == "place" {
-                                    println!("[Socket] Received place command from player {}: {}", player_id, text);
-                                    let diff = engine::apply_verb(
-                                        &self.bundle,
-                                        &mut self.state.lock(),
-                                        &json,
-                                    );
-    
-                                    let event = serde_json::json!({
-                                        "type": "event",
-                                        "t": 1,
-                                        "verb": json["verb"],
-                                        "diff": diff
-                                    });
-                                    if let Err(e) = self.tx.send(Message::Text(event.to_string())) {
-                                        println!("[Socket] ERROR: Error broadcasting event: {}", e);
-                                    }
-                                } else if json["verb"].is_string() {
-                                    println!("[Socket] ERROR: Received unsupported verb '{}' from player {}", json["verb"], player_id);
-                                } 
-                            } else {
-                                println!("[Socket] ERROR: Received command from player {} but game hasn't started yet", player_id);
+                    let (rejection, client_seq) = match serde_json::from_str::<serde_json::Value>(&text) {
+                        Ok(json) if json["type"] == "resync" => {
+                            // the client saw a gap in `seq`: send what it missed since `since`
+                            println!("[Socket] Player {} asked to resync from seq {}", player_id, json["since"]);
+                            let mut locked = sink.lock().await;
+                            let Some((messages, seq)) = self.catch_up(&seat, json["since"].as_u64()).await else {
+                                println!("[Socket] ERROR: Player {} asked to resync but no game is running", player_id);
+                                continue;
+                            };
+                            last_seq.fetch_max(seq, Ordering::SeqCst);
+                            for message in messages {
+                                if let Err(e) = locked.send(Message::Text(message)).await {
+                                    println!("[Socket] ERROR: Error sending resync to player {}: {}", player_id, e);
+                                    break;
+                                }
                             }
+                            continue;
                         },
+                        Ok(json) if json["type"] == "start" => (self.request_start(&player_id).await, json["clientSeq"].clone()),
+                        // This is a pong response, just ignore silently
+                        Ok(json) if json["type"] == "pong" => continue,
+                        Ok(json) => (self.play(&seat, &player_id, &json, &text).await, json["clientSeq"].clone()),
+                        Err(_) if text.contains("\"type\":\"pong\"") => continue,
                         Err(e) => {
-                            // Handle non-JSON text messages safely
-                            if text.contains("\"type\":\"pong\"") {
-                                // This is a pong response, just ignore silently
-                            } else {
-                                println!("[Socket] ERROR: Received invalid JSON from player {}: {}", player_id, e);
-                            }
+                            println!("[Socket] ERROR: Received invalid JSON from player {}: {}", player_id, e);
+                            let reason = format!("not valid JSON: {}", e);
+                            (Some(engine::Rejection::new(engine::RejectCode::BadMessage, reason)), serde_json::Value::Null)
+                        }
+                    };
+                    let Some(rejection) = rejection else { continue };
+                    let reply = serde_json::json!({
+                        "type": "rejected",
+                        "clientSeq": client_seq,
+                        "code": rejection.code,
+                        "reason": rejection.reason,
+                        "failedPrecondition": rejection.failed_precondition
+                    });
+                    // a failed hook is the bundle's fault, so every player hears about it;
+                    // any other rejection only concerns the sender
+                    if rejection.code == engine::RejectCode::HookFailed {
+                        if let Err(e) = self.tx.send(Outbound::everyone(reply.to_string())) {
+                            println!("[Socket] ERROR: Error broadcasting hook failure: {}", e);
                         }
+                    } else if let Err(e) = sink.lock().await.send(Message::Text(reply.to_string())).await {
+                        println!("[Socket] ERROR: Error sending rejection to player {}: {}", player_id, e);
                     }
                 },
                 Ok(Message::Ping(bytes)) => {
@@ -469,3 +656,47 @@ impl Lobby {
         println!("[Socket] Connection cleanup complete for player {}", player_id);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Lobby;
+    use crate::engine::scratch_bundle;
+    use crate::hooks::test_script::{call, module, ROUND_END};
+    use serde_json::{json, Value};
+
+    #[tokio::test]
+    async fn resync_sends_the_round_ends_it_missed() {
+        // every `go` ends the round with a point for p1, who wins on the second
+        let rules = "
+verbs:
+  go: {}
+phases:
+  - id: turn
+    activePlayer: sequential
+    verbs: [go]
+hooks:
+  p1_wins: on_after_play
+match: { winScore: 2, rounds: 5 }
+";
+        let script = module(&[("p1_wins", call(ROUND_END, Some((0, 2))))], b"p1");
+        let lobby = Lobby::new("test".to_string(), scratch_bundle(rules, Some(&script)));
+        lobby.start(vec!["ann".to_string(), "bob".to_string()]).await.unwrap();
+        let go = json!({ "verb": "go", "args": {} });
+        for _ in 0..2 {
+            assert!(lobby.play("p1", "ann", &go, "go").await.is_none());
+        }
+
+        let (messages, seq) = lobby.catch_up("p2", Some(0)).await.unwrap();
+        let messages: Vec<Value> = messages.iter().map(|m| serde_json::from_str(m).unwrap()).collect();
+        let kinds: Vec<&str> = messages.iter().map(|m| m["type"].as_str().unwrap()).collect();
+        assert_eq!(kinds, ["event", "roundEnd", "event", "roundEnd", "gameOver", "legalMoves"]);
+        assert_eq!(seq, 2);
+        assert_eq!((&messages[1]["round"], &messages[1]["seq"], &messages[1]["winner"]), (&json!(1), &json!(1), &json!("p1")));
+        assert_eq!(messages[3]["scores"], json!({ "p1": 2, "p2": 0 }));
+        assert_eq!(messages[4]["winner"], "p1");
+
+        // from after the first round end, only what came later
+        let (messages, _) = lobby.catch_up("p2", Some(1)).await.unwrap();
+        assert_eq!(messages.len(), 4);
+    }
+}
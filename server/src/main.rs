@@ -12,14 +12,50 @@ use std::sync::Arc;
 
 mod bundle;
 mod engine;
+mod entities;
+mod hooks;
 mod lobby;
+mod manifest;
 
-use bundle::BundleMap;
+use bundle::{BundleMap, HashPolicy};
 use crate::lobby::{LobbyMap, new_lobby};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let bundles = BundleMap::load_dir("./games")?;
+    // `bluefelt-core hash <bundle dir>` prints the value to put in manifest.yaml
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() == 3 && args[1] == "hash" {
+        println!("{}", bundle::content_hash(std::path::Path::new(&args[2]))?);
+        return Ok(());
+    }
+
+    let games_dir = std::env::var("BLUEFELT_GAMES_DIR").unwrap_or_else(|_| "./games".to_string());
+    let hash_policy = match std::env::var("BLUEFELT_HASH_POLICY").as_deref() {
+        Ok("warn") => HashPolicy::Warn,
+        _ => HashPolicy::Strict,
+    };
+    let bundles = BundleMap::load_dir(&games_dir, hash_policy)?;
+
+    // `bluefelt-core replay <log.json>` re-runs a downloaded match log and checks every tick
+    if args.len() == 3 && args[1] == "replay" {
+        return replay_log(&bundles, std::path::Path::new(&args[2]));
+    }
+    println!("[Bundle] Loaded games from {}: {:?}", games_dir, bundles.list_games());
+
+    // opt-in polling reload while authoring bundles, e.g. BLUEFELT_WATCH_BUNDLES=2 (seconds)
+    if let Some(secs) = std::env::var("BLUEFELT_WATCH_BUNDLES").ok().and_then(|s| s.parse::<u64>().ok()) {
+        println!("[Bundle] Watching {} for changes every {}s", games_dir, secs);
+        bundles.spawn_watcher(std::time::Duration::from_secs(secs.max(1)));
+    }
+    let limits = hooks::limits();
+    println!(
+        "[Hooks] Limits per tick: {} fuel, {:?} timeout; {} MiB memory; on failure: {:?}",
+        limits.fuel, limits.timeout, limits.memory_bytes >> 20, limits.on_failure
+    );
+    let admin_token = std::env::var("BLUEFELT_ADMIN_TOKEN").ok().filter(|t| !t.is_empty());
+    if admin_token.is_none() {
+        println!("[HTTP] WARNING: BLUEFELT_ADMIN_TOKEN is not set; admin routes will refuse every request");
+    }
     
     // Wrap the DashMap in an Arc to ensure proper sharing between requests
     let lobbies = Arc::new(LobbyMap::default());
@@ -27,8 +63,12 @@ async fn main() -> anyhow::Result<()> {
     // Clone for each route handler
     let bundles_for_games = bundles.clone();
     let bundles_for_lobbies = bundles.clone();
+    let bundles_for_admin = bundles.clone();
+    let bundles_for_reload = bundles.clone();
+    let token_for_admin = admin_token.clone();
     let lobbies_for_lobbies_route = lobbies.clone();
     let lobbies_for_ws = lobbies.clone();
+    let lobbies_for_log = lobbies.clone();
 
     // Improved CORS configuration for WebSocket support
     let cors = CorsLayer::new()
@@ -45,6 +85,15 @@ async fn main() -> anyhow::Result<()> {
         ).get(
             move || list_lobbies(lobbies_for_lobbies_route.clone())
         ))
+        .route("/admin/bundles", get(
+            move |headers| admin_bundles(headers, bundles_for_admin.clone(), token_for_admin.clone())
+        ))
+        .route("/admin/bundles/reload", post(
+            move |headers| reload_bundles(headers, bundles_for_reload.clone(), admin_token.clone())
+        ))
+        .route("/lobbies/:id/log", get(
+            move |path, query| match_log(path, query, lobbies_for_log.clone())
+        ))
         .route("/lobbies/:id/ws", get(
             move |path, ws, query| ws_handler(path, ws, query, lobbies_for_ws.clone())
         ))
@@ -66,21 +115,43 @@ async fn create_lobby(
     lobbies: Arc<LobbyMap>,
 ) -> impl IntoResponse {
     let game_id = req["gameId"].as_str().unwrap_or("tic-tac-toe");
-    let bundle = match bundles.get_latest(game_id) {
+
+    // optional semver requirement, e.g. "=1.0.0" or "~1.0"; latest when absent
+    let bundle = match req["version"].as_str() {
+        Some(version) => {
+            let version_req = match semver::VersionReq::parse(version) {
+                Ok(r) => r,
+                Err(e) => {
+                    return Json(serde_json::json!({
+                        "error": format!("Invalid version requirement {:?}: {}", version, e)
+                    }));
+                }
+            };
+            bundles.get(game_id, &version_req)
+        }
+        None => bundles.get_latest(game_id),
+    };
+    let bundle = match bundle {
         Some(b) => b,
         None => {
             return Json(serde_json::json!({ 
-                "error": format!("Unknown game: {}", game_id) 
+                "error": format!("Unknown game or version: {} {}", game_id, req["version"].as_str().unwrap_or("")) 
             }));
         }
     };
     
     let id = Uuid::new_v4().to_string();
-    println!("[HTTP] Creating new lobby: {} for game: {}", id, game_id);
+    println!("[HTTP] Creating new lobby: {} for game: {} {}", id, game_id, bundle.version);
     
+    let response = serde_json::json!({
+        "id": id,
+        "game_id": game_id,
+        "version": bundle.version.to_string(),
+        "bundle_hash": bundle.hash,
+    });
     lobbies.insert(id.clone(), new_lobby(id.clone(), bundle));
     
-    Json(serde_json::json!({ "id": id, "game_id": game_id }))
+    Json(response)
 }
 
 async fn list_lobbies(
@@ -94,6 +165,8 @@ async fn list_lobbies(
             serde_json::json!({
                 "id": l.key(),
                 "game_id": lobby.bundle.game_id,
+                "version": lobby.bundle.version.to_string(),
+                "bundle_hash": lobby.bundle.hash,
                 "name": format!("{} - Lobby {}", lobby.bundle.game_id, &l.key()[0..6]),
                 "players": lobby.player_list(),
                 "started": lobby.is_started()
@@ -108,16 +181,115 @@ async fn list_games(
     bundles: BundleMap,
 ) -> impl IntoResponse {
     let games = bundles.list_games();
-    let game_list = games.iter().map(|game_id| {
+    let game_list = games.iter().filter_map(|game_id| bundles.get_latest(game_id)).map(|bundle| {
+        let meta = &bundle.manifest.metadata;
         serde_json::json!({
-            "id": game_id,
-            "name": game_id, // You might want to include more metadata
+            "id": bundle.game_id,
+            "name": meta.name,
+            "version": bundle.version.to_string(),
+            "versions": bundles.versions(&bundle.game_id).iter().map(|v| v.to_string()).collect::<Vec<_>>(),
+            "author": meta.author,
+            "players": { "min": meta.players.min, "max": meta.players.max },
+            "description": meta.description,
         })
     }).collect::<Vec<_>>();
     
     Json(game_list)
 }
 
+/// Event log of a finished match, or with `?tick=N` the state after tick N.
+async fn match_log(
+    Path(id): Path<String>,
+    axum::extract::Query(params): axum::extract::Query<std::collections::HashMap<String, String>>,
+    lobbies: Arc<LobbyMap>,
+) -> axum::response::Response {
+    let Some(lobby) = lobbies.get(&id).map(|l| l.clone()) else {
+        return (http::StatusCode::NOT_FOUND, Json(serde_json::json!({ "error": "Lobby does not exist" }))).into_response();
+    };
+    let Some(log) = lobby.finished_log().await else {
+        return (
+            http::StatusCode::CONFLICT,
+            Json(serde_json::json!({ "error": "The log is available once the match is over" })),
+        ).into_response();
+    };
+    println!("[HTTP] Match log requested for lobby {}", id);
+    match params.get("tick").map(|t| t.parse::<u64>()) {
+        None => Json(log).into_response(),
+        Some(Ok(tick)) => match log.state_at(tick) {
+            Ok(state) => Json(serde_json::json!({ "tick": tick, "state": state })).into_response(),
+            Err(e) => (
+                http::StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": format!("{:#}", e) })),
+            ).into_response(),
+        },
+        Some(Err(_)) => (
+            http::StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "tick must be a number" })),
+        ).into_response(),
+    }
+}
+
+/* ---------- admin ---------- */
+
+/// Admin routes need BLUEFELT_ADMIN_TOKEN to be set and the request to carry it
+/// in `x-admin-token`; without a configured token they answer 403. Returns the
+/// response to send when the request is refused.
+fn admin_refusal(headers: &http::HeaderMap, token: &Option<String>) -> Option<axum::response::Response> {
+    let Some(token) = token else {
+        return Some((
+            http::StatusCode::FORBIDDEN,
+            Json(serde_json::json!({ "error": "Admin routes are disabled; set BLUEFELT_ADMIN_TOKEN to enable them" })),
+        ).into_response());
+    };
+    let given = headers.get("x-admin-token").map(|v| v.as_bytes()).unwrap_or_default();
+    if tokens_match(given, token.as_bytes()) {
+        return None;
+    }
+    Some((http::StatusCode::UNAUTHORIZED, Json(serde_json::json!({ "error": "Unauthorized" }))).into_response())
+}
+
+/// Compare digests of both tokens byte by byte without stopping early, so the
+/// time taken says nothing about how much of the token was right.
+fn tokens_match(given: &[u8], token: &[u8]) -> bool {
+    use sha2::{Digest, Sha256};
+    let (a, b) = (Sha256::digest(given), Sha256::digest(token));
+    a.iter().zip(b.iter()).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+async fn admin_bundles(
+    headers: http::HeaderMap,
+    bundles: BundleMap,
+    token: Option<String>,
+) -> axum::response::Response {
+    if let Some(refusal) = admin_refusal(&headers, &token) {
+        return refusal;
+    }
+    Json(bundles.report()).into_response()
+}
+
+/// Re-scan the games directory. New lobbies get the new builds; running ones keep theirs.
+async fn reload_bundles(
+    headers: http::HeaderMap,
+    bundles: BundleMap,
+    token: Option<String>,
+) -> axum::response::Response {
+    if let Some(refusal) = admin_refusal(&headers, &token) {
+        return refusal;
+    }
+    println!("[HTTP] Bundle reload requested");
+    match tokio::task::spawn_blocking(move || bundles.reload()).await {
+        Ok(Ok(report)) => Json(report).into_response(),
+        Ok(Err(e)) => (
+            http::StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": format!("{:#}", e) })),
+        ).into_response(),
+        Err(e) => (
+            http::StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": format!("Reload task failed: {}", e) })),
+        ).into_response(),
+    }
+}
+
 /* ---------- WS ---------- */
 
 async fn ws_handler(
@@ -155,7 +327,7 @@ async fn ws_handler(
     );
     
     // Add player to the lobby
-    let added = lobby.add_player(player_id.clone());
+    let added = lobby.add_player(player_id.clone()).await;
     
     println!("[Socket] Player {} attempted to join lobby {}. Added: {}", player_id, id, added);
     
@@ -172,6 +344,23 @@ async fn ws_handler(
     
     ws.on_upgrade(move |sock| async move {
         println!("[Socket] WebSocket connections successful for player {} in lobby {}", player_id, id);
-        lobby.accept_client(sock).await;
+        lobby.accept_client(sock, player_id).await;
     })
 }
+
+/* ---------- CLI ---------- */
+
+/// Play a match log again on the bundle build it names and report whether it reproduces.
+fn replay_log(bundles: &BundleMap, path: &std::path::Path) -> anyhow::Result<()> {
+    use anyhow::Context;
+    let text = std::fs::read_to_string(path).with_context(|| format!("cannot read {}", path.display()))?;
+    let log: engine::MatchLog = serde_json::from_str(&text).with_context(|| format!("{} is not a match log", path.display()))?;
+    let version = semver::VersionReq::parse(&format!("={}", log.version))?;
+    let bundle = bundles
+        .get(&log.game_id, &version)
+        .with_context(|| format!("{} {} is not loaded", log.game_id, log.version))?;
+    let game = engine::Match::replay(bundle, &log)?;
+    println!("Replayed {} ticks of {} {}: every diff matches", log.events.len(), log.game_id, log.version);
+    println!("{}", serde_json::to_string_pretty(&game.state)?);
+    Ok(())
+}
@@ -0,0 +1,172 @@
+//! manifest.rs – typed model of a bundle's `manifest.yaml`
+
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+
+/// Spec versions this server can run. `"1"` is the spelling used in
+/// development.md and describes the same grammar as RFC 0001 (`"0.1"`).
+pub const SUPPORTED_SPEC_VERSIONS: &[SpecVersion] = &[
+    SpecVersion { major: 0, minor: 1 },
+    SpecVersion { major: 1, minor: 0 },
+];
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    pub game_id: String,
+    pub version: semver::Version,
+    #[serde(alias = "bluefeltSpecVersion")]
+    pub spec_version: SpecVersion,
+    pub metadata: Metadata,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Metadata {
+    pub name: String,
+    /// `designer` is accepted as in the development guide example.
+    #[serde(default, alias = "designer")]
+    pub author: Option<String>,
+    pub players: PlayerCount,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+pub struct PlayerCount {
+    pub min: u32,
+    pub max: u32,
+}
+
+/// `specVersion` as `major.minor`. YAML authors write it quoted (`"0.1"`),
+/// bare (`0.1`) or as a single number (`1`), so all three are accepted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpecVersion {
+    pub major: u64,
+    pub minor: u64,
+}
+
+impl Manifest {
+    /// Read, parse and validate `manifest.yaml`.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("cannot read {}", path.display()))?;
+        let manifest: Manifest = serde_yaml::from_str(&text)
+            .with_context(|| format!("invalid manifest {}", path.display()))?;
+        manifest
+            .validate()
+            .with_context(|| format!("invalid manifest {}", path.display()))?;
+        Ok(manifest)
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.game_id.is_empty() {
+            bail!("gameId must not be empty");
+        }
+        if !SUPPORTED_SPEC_VERSIONS.contains(&self.spec_version) {
+            let supported: Vec<String> = SUPPORTED_SPEC_VERSIONS.iter().map(|v| v.to_string()).collect();
+            bail!(
+                "specVersion {} is not supported by this server (supported: {})",
+                self.spec_version,
+                supported.join(", ")
+            );
+        }
+        let players = self.metadata.players;
+        if players.min == 0 || players.min > players.max {
+            bail!("metadata.players must satisfy 1 <= min <= max (got min {}, max {})", players.min, players.max);
+        }
+        Ok(())
+    }
+}
+
+impl SpecVersion {
+    fn parse(s: &str) -> Option<Self> {
+        let (major, minor) = match s.trim().split_once('.') {
+            Some((major, minor)) => (major, minor),
+            None => (s.trim(), "0"),
+        };
+        Some(Self { major: major.parse().ok()?, minor: minor.parse().ok()? })
+    }
+}
+
+impl fmt::Display for SpecVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+impl<'de> Deserialize<'de> for SpecVersion {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // numbers go through their textual form so `0.1` stays `0.1`, not 0.1000000001
+        let raw = match serde_yaml::Value::deserialize(deserializer)? {
+            serde_yaml::Value::String(s) => s,
+            serde_yaml::Value::Number(n) => n.to_string(),
+            other => {
+                return Err(serde::de::Error::custom(format!(
+                    "specVersion must be a string like \"0.1\", got {:?}",
+                    other
+                )))
+            }
+        };
+        SpecVersion::parse(&raw).ok_or_else(|| {
+            serde::de::Error::custom(format!("specVersion `{}` is not of the form major.minor", raw))
+        })
+    }
+}
+
+impl Serialize for SpecVersion {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A manifest with `spec` as its spec-version line, parsed and validated.
+    fn parse(spec: &str) -> anyhow::Result<Manifest> {
+        let yaml = format!(
+            "gameId: \"demo\"\nversion: \"1.0.0\"\n{}\nmetadata:\n  name: \"Demo\"\n  players: {{ min: 2, max: 4 }}\n",
+            spec
+        );
+        let manifest: Manifest = serde_yaml::from_str(&yaml)?;
+        manifest.validate()?;
+        Ok(manifest)
+    }
+
+    #[test]
+    fn spec_version_0_1_parses_quoted_and_bare() {
+        for spec in ["specVersion: \"0.1\"", "specVersion: 0.1"] {
+            assert_eq!(parse(spec).unwrap().spec_version, SpecVersion { major: 0, minor: 1 }, "{}", spec);
+        }
+    }
+
+    #[test]
+    fn spec_version_1_0_parses_with_or_without_minor() {
+        for spec in ["specVersion: \"1\"", "specVersion: 1", "specVersion: \"1.0\""] {
+            assert_eq!(parse(spec).unwrap().spec_version, SpecVersion { major: 1, minor: 0 }, "{}", spec);
+        }
+    }
+
+    #[test]
+    fn bluefelt_spec_version_is_an_alias() {
+        let manifest = parse("bluefeltSpecVersion: \"0.1\"").unwrap();
+        assert_eq!(manifest.spec_version, SpecVersion { major: 0, minor: 1 });
+    }
+
+    #[test]
+    fn unsupported_major_version_is_refused_with_the_supported_list() {
+        let err = parse("specVersion: \"2.0\"").unwrap_err();
+        assert_eq!(err.to_string(), "specVersion 2.0 is not supported by this server (supported: 0.1, 1.0)");
+    }
+
+    #[test]
+    fn malformed_spec_version_names_the_value() {
+        let err = parse("specVersion: \"one\"").unwrap_err();
+        assert!(err.to_string().contains("specVersion `one` is not of the form major.minor"), "{}", err);
+    }
+}